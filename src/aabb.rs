@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub minimum: Vec3,
+    pub maximum: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        // The empty box, which leaves any box it is combined with unchanged.
+        Aabb {
+            minimum: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            maximum: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+}
+
+impl Aabb {
+    pub fn new(a: Vec3, b: Vec3) -> Aabb {
+        // Treat the two points a and b as extrema for the bounding box, so we don't require a
+        // particular minimum/maximum coordinate order.
+        Aabb {
+            minimum: Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            maximum: Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    pub fn surrounding(box0: &Aabb, box1: &Aabb) -> Aabb {
+        Aabb {
+            minimum: Vec3::new(
+                box0.minimum.x().min(box1.minimum.x()),
+                box0.minimum.y().min(box1.minimum.y()),
+                box0.minimum.z().min(box1.minimum.z()),
+            ),
+            maximum: Vec3::new(
+                box0.maximum.x().max(box1.maximum.x()),
+                box0.maximum.y().max(box1.maximum.y()),
+                box0.maximum.z().max(box1.maximum.z()),
+            ),
+        }
+    }
+
+    pub fn overlap(box0: &Aabb, box1: &Aabb) -> Aabb {
+        // Left inverted, and so empty, when the boxes don't overlap.
+        Aabb {
+            minimum: Vec3::new(
+                box0.minimum.x().max(box1.minimum.x()),
+                box0.minimum.y().max(box1.minimum.y()),
+                box0.minimum.z().max(box1.minimum.z()),
+            ),
+            maximum: Vec3::new(
+                box0.maximum.x().min(box1.maximum.x()),
+                box0.maximum.y().min(box1.maximum.y()),
+                box0.maximum.z().min(box1.maximum.z()),
+            ),
+        }
+    }
+
+    pub fn pad(&self) -> Aabb {
+        // Return an AABB that has no side narrower than some delta, padding if necessary, so
+        // planar objects still get a box the slab test can hit.
+        let delta = 0.0001;
+        let mut minimum = [0.0; 3];
+        let mut maximum = [0.0; 3];
+        for axis in 0..3 {
+            let (lo, hi) = (self.minimum[axis], self.maximum[axis]);
+            if hi - lo < delta {
+                minimum[axis] = lo - delta / 2.0;
+                maximum[axis] = hi + delta / 2.0;
+            } else {
+                minimum[axis] = lo;
+                maximum[axis] = hi;
+            }
+        }
+        Aabb {
+            minimum: Vec3::from(minimum),
+            maximum: Vec3::from(maximum),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        // Zero for the empty box.
+        let extent = self.maximum - self.minimum;
+        if extent.x() < 0.0 || extent.y() < 0.0 || extent.z() < 0.0 {
+            return 0.0;
+        }
+        2.0 * (extent.x() * extent.y() + extent.y() * extent.z() + extent.z() * extent.x())
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.maximum - self.minimum;
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, r: &Ray, ray_t: Range<f32>) -> bool {
+        self.interval(r, ray_t).is_some()
+    }
+
+    pub fn interval(&self, r: &Ray, ray_t: Range<f32>) -> Option<Range<f32>> {
+        // Slab test: intersect the ray's parameter interval with each axis-aligned slab in turn,
+        // leaving the part of the interval inside the box.
+        let mut t_min = ray_t.start;
+        let mut t_max = ray_t.end;
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction()[axis];
+            let mut t0 = (self.minimum[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.maximum[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max <= t_min {
+                return None;
+            }
+        }
+        Some(t_min..t_max)
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use crate::daylight::Daylight;
+use crate::environment::EnvironmentMap;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Default)]
+pub enum Background {
+    #[default]
+    Sky, // Blue-white gradient from the horizon up
+    Color(Vec3),
+    Environment(Arc<EnvironmentMap>),
+    Daylight(Daylight), // Physical sky and sun
+}
+
+impl Background {
+    pub fn value(&self, r: &Ray) -> Vec3 {
+        // Radiance arriving along a ray that escapes the scene.
+        match self {
+            Background::Sky => {
+                let unit_direction: Vec3 = Vec3::unit_vector(r.direction());
+                let a: f32 = 0.5 * (unit_direction.y() + 1.0);
+
+                (1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0)
+            }
+            Background::Color(color) => *color,
+            Background::Environment(map) => map.value(r.direction()),
+            Background::Daylight(daylight) => daylight.value(r.direction()),
+        }
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        // Whether the integrator samples directions towards the background like a light. The
+        // smooth backgrounds are found well enough by the scattered rays.
+        matches!(self, Background::Environment(_) | Background::Daylight(_))
+    }
+
+    pub fn pdf_value(&self, direction: Vec3) -> f32 {
+        match self {
+            Background::Environment(map) => map.pdf_value(direction),
+            Background::Daylight(daylight) => daylight.pdf_value(direction),
+            _ => 0.0,
+        }
+    }
+
+    pub fn random(&self) -> Vec3 {
+        match self {
+            Background::Environment(map) => map.random(),
+            Background::Daylight(daylight) => daylight.random(),
+            _ => Vec3::random_unit_vector(),
+        }
+    }
+}
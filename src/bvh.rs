@@ -0,0 +1,198 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::hittable_list::HittableList;
+use crate::ray::Ray;
+
+const BINS: usize = 12; // Candidate positions along the axis for splitting the objects
+
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    right: Option<Box<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> BvhNode {
+        BvhNode::build(list.objects)
+    }
+
+    fn build(mut objects: Vec<Box<dyn Hittable>>) -> BvhNode {
+        if objects.len() <= 2 {
+            let right = if objects.len() == 2 {
+                objects.pop()
+            } else {
+                None
+            };
+            let left = objects
+                .pop()
+                .unwrap_or_else(|| Box::new(HittableList::default()));
+            let bbox = match &right {
+                Some(right) => Aabb::surrounding(&left.bounding_box(), &right.bounding_box()),
+                None => left.bounding_box(),
+            };
+            return BvhNode { left, right, bbox };
+        }
+
+        // Split along the longest axis of the box holding the object centroids, where the
+        // surface area heuristic expects the cheapest hit tests: the objects in each half
+        // weighted by the chance of a ray crossing its box, which goes with the box's area. The
+        // splits tried are between bins of centroids along the axis. Without a split to try, as
+        // when the centroids all coincide, the objects are halved.
+        let centroid_bounds = objects.iter().fold(Aabb::default(), |acc, object| {
+            let c = object.bounding_box().centroid();
+            Aabb::surrounding(&acc, &Aabb::new(c, c))
+        });
+        let axis = centroid_bounds.longest_axis();
+        let start = centroid_bounds.minimum[axis];
+        let extent = centroid_bounds.maximum[axis] - start;
+        let bin = |object: &dyn Hittable| {
+            let c = object.bounding_box().centroid()[axis];
+            (((c - start) / extent * BINS as f32) as usize).min(BINS - 1)
+        };
+
+        let right_objects = match (extent > 0.0)
+            .then(|| cheapest_split(&objects, bin))
+            .flatten()
+        {
+            Some(split) => {
+                let (left, right) = objects
+                    .into_iter()
+                    .partition(|object| bin(object.as_ref()) < split);
+                objects = left;
+                right
+            }
+            None => objects.split_off(objects.len() / 2),
+        };
+        let left = BvhNode::build(objects);
+        let right = BvhNode::build(right_objects);
+        let bbox = Aabb::surrounding(&left.bbox, &right.bbox);
+
+        BvhNode {
+            left: Box::new(left),
+            right: Some(Box::new(right)),
+            bbox,
+        }
+    }
+}
+
+fn cheapest_split(
+    objects: &[Box<dyn Hittable>],
+    bin: impl Fn(&dyn Hittable) -> usize,
+) -> Option<usize> {
+    // The first bin of the right half for the split with the lowest cost, if any has objects
+    // on both sides.
+    let mut bins = [(Aabb::default(), 0); BINS];
+    for object in objects {
+        let (bbox, count) = &mut bins[bin(object.as_ref())];
+        *bbox = Aabb::surrounding(bbox, &object.bounding_box());
+        *count += 1;
+    }
+
+    let cost = |bins: &[(Aabb, usize)]| {
+        let (bbox, count) = bins
+            .iter()
+            .fold((Aabb::default(), 0), |(acc, n), (bbox, count)| {
+                (Aabb::surrounding(&acc, bbox), n + count)
+            });
+        (count, bbox.surface_area() * count as f32)
+    };
+    (1..BINS)
+        .filter_map(|split| {
+            let (left_count, left_cost) = cost(&bins[..split]);
+            let (right_count, right_cost) = cost(&bins[split..]);
+            let total = left_cost + right_cost;
+            (left_count > 0 && right_count > 0 && total.is_finite()).then_some((split, total))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(split, _)| split)
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
+        if !self.bbox.hit(r, ray_t.clone()) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(r, ray_t.clone(), depth);
+        let closest_so_far = hit_left.map_or(ray_t.end, |rec| rec.t);
+        let hit_right = self.right.as_ref().and_then(|right| {
+            right.hit(
+                r,
+                Range {
+                    start: ray_t.start,
+                    end: closest_so_far,
+                },
+                depth,
+            )
+        });
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> f32 {
+        if !self.bbox.hit(r, ray_t.clone()) {
+            return 1.0;
+        }
+        let right = self
+            .right
+            .as_ref()
+            .map_or(1.0, |right| right.transmittance(r, ray_t.clone(), depth));
+        self.left.transmittance(r, ray_t, depth) * right
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::rng::{self, random_range};
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    fn random_spheres() -> HittableList {
+        // Overlapping spheres of mixed sizes, some of them far apart.
+        let mut list = HittableList::default();
+        for _ in 0..200 {
+            let center = Vec3::random(-10.0, 10.0);
+            let radius = random_range(0.05..1.5);
+            let material = Material::Dielectric { ir: 1.5 };
+            list.add(Box::new(Sphere::new(center, radius, material)));
+        }
+        list
+    }
+
+    #[test]
+    fn matches_linear_list() {
+        rng::seed(11);
+        let list = random_spheres();
+        rng::seed(11);
+        let bvh = BvhNode::new(random_spheres());
+
+        let mut hits = 0;
+        for _ in 0..5000 {
+            let origin = Vec3::random(-15.0, 15.0);
+            let direction = Vec3::random_unit_vector();
+            let r = Ray::new(origin, direction);
+            let expected = list.hit(&r, 0.001..f32::INFINITY, 0);
+            let found = bvh.hit(&r, 0.001..f32::INFINITY, 0);
+            match (expected, found) {
+                (None, None) => {}
+                (Some(expected), Some(found)) => {
+                    assert_eq!(expected.t, found.t);
+                    assert_eq!(expected.p, found.p);
+                    assert_eq!(expected.normal, found.normal);
+                    hits += 1;
+                }
+                (expected, found) => panic!("list {expected:?}, BVH {found:?} along {r:?}"),
+            }
+        }
+        // Enough of the rays hit something for the comparison to mean something.
+        assert!(hits > 1000, "only {hits} hits");
+    }
+}
@@ -1,25 +1,17 @@
+use crate::background::Background;
+use crate::framebuffer::Framebuffer;
+use crate::hittable::Hittable;
 use crate::hittable_list::HittableList;
+use crate::light::Light;
+use crate::medium::Fog;
 use crate::ray::Ray;
+use crate::rng::{self, random};
 use crate::utils::*;
 use crate::vec3::Vec3;
-use rand::prelude::*;
-use std::ops::Range;
-
-fn clamp(rng: &Range<f32>, val: f32) -> f32 {
-    if val < rng.start {
-        rng.start
-    } else if val > rng.end {
-        rng.end
-    } else {
-        return val;
-    }
-}
-
-fn linear_to_gama(linear_component: f32) -> f32 {
-    return linear_component.sqrt();
-}
+use std::sync::Mutex;
+use std::thread;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Camera {
     pub aspect_ratio: f32,      // Ratio of image width over height
     pub image_width: i32,       // Rendered image width in pixel count
@@ -31,6 +23,12 @@ pub struct Camera {
     pub vup: Vec3,              // Camera-relative "up" direction
     pub defocus_angle: f32,     // Variation angle of rays through each pixel
     pub focus_dist: f32,        // Distance from camera lookfrom point to plane of perfect focus
+    pub shutter_open: f32,      // Time the shutter opens
+    pub shutter_close: f32,     // Time the shutter closes, rays get random times in between
+    pub threads: usize,         // Number of render worker threads (0 uses all available cores)
+    pub seed: Option<u64>,      // Seed for reproducible renders (None seeds from OS entropy)
+    pub background: Background, // Radiance arriving from outside the scene
+    pub fog: Option<Fog>,       // Fog filling the space between surfaces
     image_height: i32,
     center: Vec3,
     pixel00_loc: Vec3,
@@ -44,52 +42,77 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn render(&mut self, world: &HittableList) {
+    pub fn render(
+        &mut self,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        delta_lights: &[Box<dyn Light>],
+    ) -> Framebuffer {
         // Initialize camera
         self.initialize();
         // Render
-        println!("P3\n{} {}\n{}", self.image_width, self.image_height, 255);
-        for j in (0..self.image_height).rev() {
-            for i in 0..self.image_width {
-                let mut pixel_color = Vec3::default();
-                for _ in 0..self.samples_per_pixel {
-                    let r: Ray = self.get_ray(i, j);
-                    pixel_color = pixel_color + color(&r, self.max_deph, &world);
-                }
-
-                self.write_color(pixel_color, self.samples_per_pixel);
+        let mut image = Framebuffer::new(self.image_width as usize, self.image_height as usize);
+        let height = image.height;
+        // Scanlines are stored top to bottom. Each worker pulls the next unrendered scanline
+        // from the shared iterator until none are left.
+        let scanlines = Mutex::new(image.scanlines_mut().enumerate());
+
+        thread::scope(|s| {
+            for _ in 0..self.thread_count().min(height) {
+                s.spawn(|| loop {
+                    let next = scanlines.lock().unwrap().next();
+                    let Some((row, scanline)) = next else {
+                        break;
+                    };
+                    self.render_scanline(world, lights, delta_lights, row as i32, scanline);
+                });
+            }
+        });
+
+        image
+    }
+
+    fn render_scanline(
+        &self,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        delta_lights: &[Box<dyn Light>],
+        j: i32,
+        scanline: &mut [Vec3],
+    ) {
+        // Reseed per scanline rather than per worker, so the image doesn't depend on which
+        // worker happened to pick up which scanline.
+        if let Some(seed) = self.seed {
+            rng::seed(seed ^ (j as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        }
+        for (i, pixel) in scanline.iter_mut().enumerate() {
+            let mut pixel_color = Vec3::default();
+            for _ in 0..self.samples_per_pixel {
+                let r: Ray = self.get_ray(i as i32, j);
+                pixel_color = pixel_color
+                    + color(
+                        &r,
+                        self.max_deph,
+                        world,
+                        lights,
+                        delta_lights,
+                        &self.background,
+                        self.fog.as_ref(),
+                    );
             }
+            // Divide the color by the number of samples.
+            *pixel = pixel_color / self.samples_per_pixel as f32;
         }
     }
-    pub fn write_color(self, pixel_color: Vec3, samples_per_pixel: i32) {
-        let mut r = pixel_color.x();
-        let mut g = pixel_color.y();
-        let mut b = pixel_color.z();
-
-        // Divide the color by the number of samples.
-
-        let scale = 1.0 / samples_per_pixel as f32;
-        r *= scale;
-        g *= scale;
-        b *= scale;
-
-        // Apply the linear to gamma transform.
-        r = linear_to_gama(r);
-        g = linear_to_gama(g);
-        b = linear_to_gama(b);
-
-        // Write the translated [0, 255] value of each color component.
-        let intensity: Range<f32> = Range {
-            start: 0.000,
-            end: 0.999,
-        };
-        println!(
-            "{} {} {}",
-            (255.99 * clamp(&intensity, r)) as i32,
-            (255.99 * clamp(&intensity, g)) as i32,
-            (255.99 * clamp(&intensity, b)) as i32
-        )
+
+    fn thread_count(&self) -> usize {
+        if self.threads > 0 {
+            self.threads
+        } else {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        }
     }
+
     pub fn initialize(&mut self) {
         // image size
         self.image_height = std::cmp::max((self.image_width as f32 / self.aspect_ratio) as i32, 1);
@@ -108,7 +131,7 @@ impl Camera {
         self.v = Vec3::cross(&self.w, &self.u);
 
         // Calculate the vectors across the horizontal and down the vertical viewport edges.
-        let viewport_u: Vec3 = viewport_width * -self.u; // Vector across viewport horizontal edge
+        let viewport_u: Vec3 = viewport_width * self.u; // Vector across viewport horizontal edge
         let viewport_v: Vec3 = viewport_height * -self.v; // Vector down viewport vertical edge
 
         // Calculate the horizontal and vertical delta vectors from pixel to pixel.
@@ -126,7 +149,7 @@ impl Camera {
         self.defocus_disk_v = self.v * defocus_radius;
     }
 
-    pub fn get_ray(self, i: i32, j: i32) -> Ray {
+    pub fn get_ray(&self, i: i32, j: i32) -> Ray {
         // Get a randomly-sampled camera ray for the pixel at location i,j, originating from
         // the camera defocus disk.
         let pixel_center: Vec3 =
@@ -139,21 +162,22 @@ impl Camera {
             self.defocus_disk_sample()
         };
         let ray_direction: Vec3 = pixel_sample - ray_origin;
+        let ray_time =
+            self.shutter_open + random::<f32>() * (self.shutter_close - self.shutter_open);
 
-        Ray::ray(ray_origin, ray_direction)
+        Ray::with_time(ray_origin, ray_direction, ray_time)
     }
 
-    fn defocus_disk_sample(self) -> Vec3 {
+    fn defocus_disk_sample(&self) -> Vec3 {
         // Returns a random point in the camera defocus disk.
         let p = Vec3::random_in_unit_disk();
-        return self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v);
+        self.center + (p.x() * self.defocus_disk_u) + (p.y() * self.defocus_disk_v)
     }
 
     fn pixel_sample_square(&self) -> Vec3 {
-        let mut rng = rand::thread_rng();
         // Returns a random point in the square surrounding a pixel at the origin.
-        let px = -0.5 + rng.gen::<f32>();
-        let py = -0.5 + rng.gen::<f32>();
+        let px = -0.5 + random::<f32>();
+        let py = -0.5 + random::<f32>();
         (px * self.pixel_delta_u) + (py * self.pixel_delta_v)
     }
 }
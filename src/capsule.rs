@@ -0,0 +1,143 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::utils::{solve_quadratic, NearestRoot};
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Points within a radius of the segment from a to b: a cylinder with a hemisphere at each end.
+#[derive(Debug, Clone)]
+pub struct Capsule {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f32,
+    pub material: Material,
+    uvw: Onb, // Frame with w along the axis from a to b
+    length: f32,
+}
+
+impl Capsule {
+    pub fn new(a: Vec3, b: Vec3, radius: f32, material: Material) -> Capsule {
+        Capsule {
+            a,
+            b,
+            radius,
+            material,
+            uvw: Onb::new(b - a),
+            length: (b - a).length(),
+        }
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Intersect in the capsule's frame, with the segment along z from 0 to the length. The
+        // frame is orthonormal, so t is the same in both.
+        let o = self.uvw.local(r.origin() - self.a);
+        let d = self.uvw.local(r.direction());
+        let (radius, length) = (self.radius, self.length);
+
+        // The nearest hit in the interval. Each part only counts over its own range of z, the
+        // side between the ends and each hemisphere beyond its end.
+        let middle = Vec3::new(0.0, 0.0, 0.5 * length);
+        let mut roots = NearestRoot::new(o, d, middle, ray_t);
+        let o = roots.origin;
+
+        let a = d.x() * d.x() + d.y() * d.y();
+        let half_b = o.x() * d.x() + o.y() * d.y();
+        let c = o.x() * o.x() + o.y() * o.y() - radius * radius;
+        if let (Some((t0, t1)), true) = (solve_quadratic(a, half_b, c), a > 0.0) {
+            for t in [t0, t1] {
+                let p = o + t * d;
+                if (0.0..=length).contains(&p.z()) {
+                    roots.consider(t, p);
+                }
+            }
+        }
+
+        for end in [0.0, length] {
+            let oc = o - Vec3::new(0.0, 0.0, end);
+            let a = d.length_squared();
+            let half_b = Vec3::dot(&oc, &d);
+            let c = oc.length_squared() - radius * radius;
+            if let Some((t0, t1)) = solve_quadratic(a, half_b, c) {
+                for t in [t0, t1] {
+                    let p = o + t * d;
+                    if (end == 0.0 && p.z() <= 0.0) || (end == length && p.z() >= length) {
+                        roots.consider(t, p);
+                    }
+                }
+            }
+        }
+
+        // The normal points away from the closest point of the segment. u goes around the
+        // axis and v along it, from one tip to the other.
+        let (t, p) = roots.nearest()?;
+        let normal = (p - Vec3::new(0.0, 0.0, p.z().clamp(0.0, length))) / radius;
+        let (front_face, normal) = face_normal(r, self.uvw.transform(normal));
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            material: &self.material,
+            t,
+            u: (p.y().atan2(p.x()) + PI) / (2.0 * PI),
+            v: (p.z() + radius) / (length + 2.0 * radius),
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::surrounding(
+            &Aabb::new(self.a - rvec, self.a + rvec),
+            &Aabb::new(self.b - rvec, self.b + rvec),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grazing::{check_tangent, check_through};
+
+    // Unit radius capsule around the segment along y from -1 to 1.
+    fn capsule() -> Capsule {
+        let (a, b) = (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        Capsule::new(a, b, 1.0, Material::default())
+    }
+
+    fn distance(p: Vec3) -> f32 {
+        (p - Vec3::new(0.0, p.y().clamp(-1.0, 1.0), 0.0)).length() - 1.0
+    }
+
+    #[test]
+    fn grazing_side_wall() {
+        for y in [-0.9, 0.0, 0.5] {
+            check_tangent(&capsule(), &distance, &|offset, far| {
+                Ray::new(Vec3::new(1.0 + offset, y, -far), Vec3::new(0.0, 0.0, 1.0))
+            });
+        }
+    }
+
+    #[test]
+    fn grazing_end_plane() {
+        // Along the plane where the side meets the top hemisphere, which the surface carries on
+        // through, and past the hemisphere's tip.
+        for z in [-0.5, 0.0, 0.9] {
+            for y in [0.999, 1.0, 1.001] {
+                check_through(&capsule(), &distance, &|far| {
+                    Ray::new(Vec3::new(-far, y, z), Vec3::new(1.0, 0.0, 0.0))
+                });
+            }
+        }
+        check_tangent(&capsule(), &distance, &|offset, far| {
+            Ray::new(Vec3::new(-far, 2.0 + offset, 0.0), Vec3::new(1.0, 0.0, 0.0))
+        });
+    }
+}
@@ -0,0 +1,93 @@
+use clap::Parser;
+use std::path::PathBuf;
+
+use crate::camera::Camera;
+use crate::output::ImageFormat;
+
+/// Render a scene with the ray tracer.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// Scene file to render (renders the built-in random spheres scene if omitted)
+    pub scene: Option<PathBuf>,
+
+    /// Path of the rendered image
+    #[arg(short, long, default_value = "image.png")]
+    pub output: PathBuf,
+
+    /// Image format: png, ppm, ppm-ascii, exr, exr-half, hdr or pfm (guessed from the output
+    /// extension if omitted)
+    #[arg(short, long)]
+    pub format: Option<ImageFormat>,
+
+    /// Rendered image width in pixel count
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+    pub width: Option<i32>,
+
+    /// Ratio of image width over height
+    #[arg(long, value_parser = positive)]
+    pub aspect_ratio: Option<f32>,
+
+    /// Count of random samples for each pixel
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+    pub samples: Option<i32>,
+
+    /// Maximum number of ray bounces into scene
+    #[arg(long)]
+    pub max_depth: Option<i32>,
+
+    /// Vertical view angle (field of view) in degrees
+    #[arg(long)]
+    pub vfov: Option<f32>,
+
+    /// Number of render worker threads (defaults to all available cores)
+    #[arg(short = 'j', long)]
+    pub threads: Option<usize>,
+
+    /// Seed for the random number generator, for reproducible renders
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Render a quick preview at a quarter of the resolution with at most 16 samples per pixel
+    #[arg(long)]
+    pub preview: bool,
+}
+
+fn positive(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        Ok(_) => Err(String::from("must be a positive number")),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+impl Args {
+    pub fn apply(&self, cam: &mut Camera) {
+        // Override the scene's camera settings with the ones given on the command line.
+        if let Some(width) = self.width {
+            cam.image_width = width;
+        }
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            cam.aspect_ratio = aspect_ratio;
+        }
+        if let Some(samples) = self.samples {
+            cam.samples_per_pixel = samples;
+        }
+        if let Some(max_depth) = self.max_depth {
+            cam.max_deph = max_depth;
+        }
+        if let Some(vfov) = self.vfov {
+            cam.vfov = vfov;
+        }
+        if let Some(threads) = self.threads {
+            cam.threads = threads;
+        }
+        if self.seed.is_some() {
+            cam.seed = self.seed;
+        }
+        if self.preview {
+            cam.image_width = (cam.image_width / 4).max(1);
+            cam.samples_per_pixel = cam.samples_per_pixel.min(16);
+        }
+    }
+}
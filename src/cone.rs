@@ -0,0 +1,161 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::disk::disk_bbox;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::utils::{solve_quadratic, NearestRoot};
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Cone from a circular base to an apex, closed by a flat cap over the base if `capped`.
+#[derive(Debug, Clone)]
+pub struct Cone {
+    pub base: Vec3,
+    pub apex: Vec3,
+    pub radius: f32, // Of the base
+    pub capped: bool,
+    pub material: Material,
+    uvw: Onb, // Frame with w along the axis from the base to the apex
+    height: f32,
+}
+
+impl Cone {
+    pub fn new(base: Vec3, apex: Vec3, radius: f32, capped: bool, material: Material) -> Cone {
+        Cone {
+            base,
+            apex,
+            radius,
+            capped,
+            material,
+            uvw: Onb::new(apex - base),
+            height: (apex - base).length(),
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Intersect in the cone's frame, with the base at z = 0 and the apex at z = height. The
+        // frame is orthonormal, so t is the same in both.
+        let o = self.uvw.local(r.origin() - self.base);
+        let d = self.uvw.local(r.direction());
+        let (radius, height) = (self.radius, self.height);
+
+        // The nearest hit in the interval: t, the outward normal in the frame, u and v.
+        let middle = Vec3::new(0.0, 0.0, 0.5 * height);
+        let mut roots = NearestRoot::new(o, d, middle, ray_t);
+        let o = roots.origin;
+
+        // Side: x^2 + y^2 = k^2 (height - z)^2 with slope k, between the base and the apex.
+        // The quadratic also holds the mirrored cone beyond the apex, which the range of z
+        // rules out. Rays parallel to the side have a = 0 and a single root.
+        let k = radius / height;
+        let s = height - o.z();
+        let a = d.x() * d.x() + d.y() * d.y() - k * k * d.z() * d.z();
+        let half_b = o.x() * d.x() + o.y() * d.y() + k * k * s * d.z();
+        let c = o.x() * o.x() + o.y() * o.y() - k * k * s * s;
+        if let Some((t0, t1)) = solve_quadratic(a, half_b, c) {
+            for t in [t0, t1] {
+                let p = o + t * d;
+                if (0.0..=height).contains(&p.z()) {
+                    // The gradient of the implicit surface, undefined at the apex itself.
+                    let gradient = Vec3::new(p.x(), p.y(), k * k * (height - p.z()));
+                    let normal = if gradient.near_zero() {
+                        Vec3::new(0.0, 0.0, 1.0)
+                    } else {
+                        Vec3::unit_vector(gradient)
+                    };
+                    let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+                    roots.consider(t, (normal, u, p.z() / height));
+                }
+            }
+        }
+
+        // Cap: the disk over the base.
+        if self.capped && d.z() != 0.0 {
+            let t = -o.z() / d.z();
+            let p = o + t * d;
+            if p.x() * p.x() + p.y() * p.y() <= radius * radius {
+                let u = 0.5 * (p.x() / radius + 1.0);
+                let v = 0.5 * (p.y() / radius + 1.0);
+                roots.consider(t, (Vec3::new(0.0, 0.0, -1.0), u, v));
+            }
+        }
+
+        let (t, (normal, u, v)) = roots.nearest()?;
+        let (front_face, normal) = face_normal(r, self.uvw.transform(normal));
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            material: &self.material,
+            t,
+            u,
+            v,
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::surrounding(
+            &disk_bbox(self.base, self.uvw.w, self.radius),
+            &Aabb::new(self.apex, self.apex),
+        )
+        .pad()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grazing::check_tangent;
+
+    // Cone standing on the unit disk around the origin, with its apex at y = 2.
+    fn cone() -> Cone {
+        let (base, apex) = (Vec3::default(), Vec3::new(0.0, 2.0, 0.0));
+        Cone::new(base, apex, 1.0, true, Material::default())
+    }
+
+    fn distance(p: Vec3) -> f32 {
+        // To the side or the base, whichever is closer, measured along the axis or across it.
+        let radial = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        let side = if (0.0..=2.0).contains(&p.y()) {
+            (radial - (1.0 - 0.5 * p.y())).abs()
+        } else {
+            f32::INFINITY
+        };
+        let base = if radial <= 1.0 {
+            p.y().abs()
+        } else {
+            f32::INFINITY
+        };
+        side.min(base)
+    }
+
+    #[test]
+    fn grazing_side_wall() {
+        // Tangent to the side's circle at a height.
+        for y in [0.2, 1.0, 1.6] {
+            let radius = 1.0 - 0.5 * y;
+            check_tangent(&cone(), &distance, &|offset, far| {
+                Ray::new(
+                    Vec3::new(radius + offset, y, -far),
+                    Vec3::new(0.0, 0.0, 1.0),
+                )
+            });
+        }
+    }
+
+    #[test]
+    fn grazing_cap_plane() {
+        for z in [-0.5, 0.0, 0.9] {
+            check_tangent(&cone(), &distance, &|offset, far| {
+                Ray::new(Vec3::new(-far, -offset, z), Vec3::new(1.0, 0.0, 0.0))
+            });
+        }
+    }
+}
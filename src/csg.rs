@@ -0,0 +1,91 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::Ray;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOp {
+    Union,        // Inside either object
+    Intersection, // Inside both objects
+    Difference,   // Inside the first object but not the second
+}
+
+// Boolean combination of two closed objects, like spheres, boxes or watertight meshes. Open
+// surfaces have no inside, and give odd results.
+pub struct Csg {
+    op: CsgOp,
+    objects: [Box<dyn Hittable>; 2],
+    bbox: Aabb,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Csg {
+        let (box_a, box_b) = (a.bounding_box(), b.bounding_box());
+        let bbox = match op {
+            CsgOp::Union => Aabb::surrounding(&box_a, &box_b),
+            CsgOp::Intersection => Aabb::overlap(&box_a, &box_b),
+            CsgOp::Difference => box_a,
+        };
+        Csg {
+            op,
+            objects: [a, b],
+            bbox,
+        }
+    }
+
+    fn contains(&self, inside: [bool; 2]) -> bool {
+        match self.op {
+            CsgOp::Union => inside[0] || inside[1],
+            CsgOp::Intersection => inside[0] && inside[1],
+            CsgOp::Difference => inside[0] && !inside[1],
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
+        if !self.bbox.hit(r, ray_t.clone()) {
+            return None;
+        }
+
+        // Walk the crossings of both objects' surfaces along the whole line of the ray, in
+        // order, tracking whether the line is inside each of them. Surfaces facing the ray are
+        // entered and the others left. The first crossing in the interval that enters or leaves
+        // the combination is its surface.
+        let after = |t: f32| Range {
+            start: t,
+            end: f32::INFINITY,
+        };
+        let mut next = [0, 1].map(|i| self.objects[i].hit(r, after(f32::NEG_INFINITY), depth));
+        let mut inside = [false; 2];
+        loop {
+            let i = match next {
+                [Some(ref a), Some(ref b)] => usize::from(b.t < a.t),
+                [Some(_), None] => 0,
+                [None, Some(_)] => 1,
+                [None, None] => return None,
+            };
+            let mut rec = next[i].take()?;
+            if rec.t >= ray_t.end {
+                return None;
+            }
+
+            let was_inside = self.contains(inside);
+            inside[i] = rec.front_face;
+            let is_inside = self.contains(inside);
+            if was_inside != is_inside && rec.t > ray_t.start {
+                // The normal already points against the ray, only which side it is changes,
+                // e.g. for the inverted surface of a subtracted object.
+                rec.front_face = is_inside;
+                return Some(rec);
+            }
+
+            next[i] = self.objects[i].hit(r, after(rec.t + 0.0001), depth);
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
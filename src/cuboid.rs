@@ -0,0 +1,83 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Axis-aligned box between two opposite corners, a closed solid.
+#[derive(Debug, Clone)]
+pub struct Cuboid {
+    pub material: Material,
+    bbox: Aabb,
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3, material: Material) -> Cuboid {
+        Cuboid {
+            material,
+            bbox: Aabb::new(a, b),
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Slab test, keeping the axes of the faces where the ray enters and leaves the box.
+        let (min, max) = (self.bbox.minimum, self.bbox.maximum);
+        let (mut t_near, mut near_axis) = (f32::NEG_INFINITY, 0);
+        let (mut t_far, mut far_axis) = (f32::INFINITY, 0);
+        for axis in 0..3 {
+            let inv_d = r.direction()[axis].recip();
+            let mut t0 = (min[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN bounds, from rays lying in a face, fail both tests.
+            if t0 > t_near {
+                (t_near, near_axis) = (t0, axis);
+            }
+            if t1 < t_far {
+                (t_far, far_axis) = (t1, axis);
+            }
+        }
+        if t_near > t_far {
+            return None;
+        }
+
+        // The entry face if it's within the interval, the exit face from inside the box.
+        let (t, axis, outward) = if ray_t.start < t_near && t_near < ray_t.end {
+            (t_near, near_axis, -r.direction()[near_axis].signum())
+        } else if ray_t.start < t_far && t_far < ray_t.end {
+            (t_far, far_axis, r.direction()[far_axis].signum())
+        } else {
+            return None;
+        };
+
+        let p = r.at(t);
+        let mut outward_normal = [0.0; 3];
+        outward_normal[axis] = outward;
+        let (front_face, normal) = face_normal(r, Vec3::from(outward_normal));
+
+        // Texture coordinates across the face, along the two other axes.
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let coordinate = |a: usize| (p[a] - min[a]) / (max[a] - min[a]);
+
+        Some(HitRecord {
+            p,
+            normal,
+            material: &self.material,
+            t,
+            u: coordinate(u_axis),
+            v: coordinate(v_axis),
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox.pad()
+    }
+}
@@ -0,0 +1,140 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::disk::disk_bbox;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::utils::{solve_quadratic, NearestRoot};
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Cylinder around the segment from a to b, closed by flat caps at both ends if `capped` and an
+// open tube otherwise.
+#[derive(Debug, Clone)]
+pub struct Cylinder {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f32,
+    pub capped: bool,
+    pub material: Material,
+    uvw: Onb, // Frame with w along the axis from a to b
+    height: f32,
+}
+
+impl Cylinder {
+    pub fn new(a: Vec3, b: Vec3, radius: f32, capped: bool, material: Material) -> Cylinder {
+        Cylinder {
+            a,
+            b,
+            radius,
+            capped,
+            material,
+            uvw: Onb::new(b - a),
+            height: (b - a).length(),
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Intersect in the cylinder's frame, with the axis along z from 0 to the height. The
+        // frame is orthonormal, so t is the same in both.
+        let o = self.uvw.local(r.origin() - self.a);
+        let d = self.uvw.local(r.direction());
+        let (radius, height) = (self.radius, self.height);
+
+        // The nearest hit in the interval: t, the outward normal in the frame, u and v.
+        let middle = Vec3::new(0.0, 0.0, 0.5 * height);
+        let mut roots = NearestRoot::new(o, d, middle, ray_t);
+        let o = roots.origin;
+
+        // Side: x^2 + y^2 = radius^2 between the caps. Rays along the axis never cross it.
+        let a = d.x() * d.x() + d.y() * d.y();
+        let half_b = o.x() * d.x() + o.y() * d.y();
+        let c = o.x() * o.x() + o.y() * o.y() - radius * radius;
+        if let (Some((t0, t1)), true) = (solve_quadratic(a, half_b, c), a > 0.0) {
+            for t in [t0, t1] {
+                let p = o + t * d;
+                if (0.0..=height).contains(&p.z()) {
+                    let normal = Vec3::new(p.x(), p.y(), 0.0) / radius;
+                    let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+                    roots.consider(t, (normal, u, p.z() / height));
+                }
+            }
+        }
+
+        // Caps: the disks at both ends of the axis.
+        if self.capped && d.z() != 0.0 {
+            for (z, side) in [(0.0, -1.0), (height, 1.0)] {
+                let t = (z - o.z()) / d.z();
+                let p = o + t * d;
+                if p.x() * p.x() + p.y() * p.y() <= radius * radius {
+                    let u = 0.5 * (p.x() / radius + 1.0);
+                    let v = 0.5 * (p.y() / radius + 1.0);
+                    roots.consider(t, (Vec3::new(0.0, 0.0, side), u, v));
+                }
+            }
+        }
+
+        let (t, (normal, u, v)) = roots.nearest()?;
+        let (front_face, normal) = face_normal(r, self.uvw.transform(normal));
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            material: &self.material,
+            t,
+            u,
+            v,
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::surrounding(
+            &disk_bbox(self.a, self.uvw.w, self.radius),
+            &disk_bbox(self.b, self.uvw.w, self.radius),
+        )
+        .pad()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grazing::check_tangent;
+
+    // Unit cylinder along y from -1 to 1.
+    fn cylinder() -> Cylinder {
+        let (a, b) = (Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        Cylinder::new(a, b, 1.0, true, Material::default())
+    }
+
+    fn distance(p: Vec3) -> f32 {
+        // To the closest point of the surface, side or caps.
+        let side = (p.x() * p.x() + p.z() * p.z()).sqrt() - 1.0;
+        let cap = p.y().abs() - 1.0;
+        Vec3::new(side.max(0.0), cap.max(0.0), 0.0).length() + side.max(cap).min(0.0)
+    }
+
+    #[test]
+    fn grazing_side_wall() {
+        for y in [-0.9, 0.0, 0.5] {
+            check_tangent(&cylinder(), &distance, &|offset, far| {
+                Ray::new(Vec3::new(1.0 + offset, y, -far), Vec3::new(0.0, 0.0, 1.0))
+            });
+        }
+    }
+
+    #[test]
+    fn grazing_cap_plane() {
+        for z in [-0.5, 0.0, 0.9] {
+            check_tangent(&cylinder(), &distance, &|offset, far| {
+                Ray::new(Vec3::new(-far, 1.0 + offset, z), Vec3::new(1.0, 0.0, 0.0))
+            });
+        }
+    }
+}
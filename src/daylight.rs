@@ -0,0 +1,195 @@
+use std::f32::consts::PI;
+
+use crate::onb::Onb;
+use crate::rng::random;
+use crate::vec3::Vec3;
+
+const SUN_RADIUS: f32 = 0.004651; // Angular radius of the sun's disk in radians
+const SUN_ILLUMINANCE: f32 = 128.0; // Sunlight above the atmosphere in klx
+const UNITS: f32 = 0.04; // Radiance per kcd/m^2, so a white surface under the sun is about white
+
+// Clear daylight sky from Preetham, Shirley and Smits' analytic model, fitted to simulations of
+// the atmosphere, with the sun as a small disk that the integrator samples like a light.
+#[derive(Debug, Clone, Copy)]
+pub struct Daylight {
+    pub strength: f32, // Multiplier of the sky's and sun's radiance
+    sun: Vec3,         // Direction towards the sun
+    sun_radiance: Vec3,
+    zenith: [f32; 3],       // Luminance Y and chromaticity x, y straight up
+    perez: [[f32; 5]; 3],   // Coefficients of the distribution of Y, x and y over the sky
+    perez_zenith: [f32; 3], // The distribution straight up, which the zenith values are at
+}
+
+impl Daylight {
+    // Elevation of the sun above the horizon and azimuth around the y axis from -z towards +x,
+    // both in radians. Turbidity measures the haze, from 2 for a very clear sky to 10 for a
+    // hazy one, the range the model is fitted to.
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32) -> Daylight {
+        let t = turbidity.clamp(1.7, 10.0);
+        let sun = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        // The model covers the sun up to the horizon. Below it, the sky is left at dusk.
+        let theta_s = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f32; 4]; 3]| {
+            let thetas = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let row = |r: [f32; 4]| (0..4).map(|i| r[i] * thetas[i]).sum::<f32>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_chroma_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let coefficients = |m: [[f32; 2]; 5]| m.map(|[slope, offset]| slope * t + offset);
+        let perez = [
+            coefficients([
+                [0.1787, -1.4630],
+                [-0.3554, 0.4275],
+                [-0.0227, 5.3251],
+                [0.1206, -2.5771],
+                [-0.0670, 0.3703],
+            ]),
+            coefficients([
+                [-0.0193, -0.2592],
+                [-0.0665, 0.0008],
+                [-0.0004, 0.2125],
+                [-0.0641, -0.8989],
+                [-0.0033, 0.0452],
+            ]),
+            coefficients([
+                [-0.0167, -0.2608],
+                [-0.0950, 0.0092],
+                [-0.0079, 0.2102],
+                [-0.0441, -1.6537],
+                [-0.0109, 0.0529],
+            ]),
+        ];
+
+        let sun_radiance = if elevation >= 0.0 {
+            sun_radiance(theta_s, t)
+        } else {
+            Vec3::default()
+        };
+
+        Daylight {
+            strength: 1.0,
+            sun,
+            sun_radiance,
+            zenith: [zenith_y.max(0.0), zenith_x, zenith_chroma_y],
+            perez,
+            perez_zenith: perez.map(|c| perez_function(c, 1.0, theta_s.cos())),
+        }
+    }
+
+    pub fn value(&self, direction: Vec3) -> Vec3 {
+        let d = Vec3::unit_vector(direction);
+        let mut radiance = self.sky(d.y(), Vec3::dot(&d, &self.sun));
+        if self.in_sun(d) {
+            radiance = radiance + self.sun_radiance;
+        }
+        self.strength * radiance
+    }
+
+    pub fn pdf_value(&self, direction: Vec3) -> f32 {
+        // Uniform density over the cone of directions towards the sun's disk.
+        if self.in_sun(Vec3::unit_vector(direction)) {
+            1.0 / sun_solid_angle()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn random(&self) -> Vec3 {
+        // A random direction towards the sun's disk. Its cosines are all too close to 1 for f32,
+        // so the offset from the center is sampled by one minus the cosine.
+        let one_minus_cos = random::<f32>() * sun_one_minus_cos();
+        let sin_theta = (one_minus_cos * (2.0 - one_minus_cos)).sqrt();
+        let phi = 2.0 * PI * random::<f32>();
+        let uvw = Onb::new(self.sun);
+        uvw.transform(Vec3::new(
+            phi.cos() * sin_theta,
+            phi.sin() * sin_theta,
+            1.0 - one_minus_cos,
+        ))
+    }
+
+    fn in_sun(&self, d: Vec3) -> bool {
+        // One minus the cosine of the angle from the sun's center, from the chord between the
+        // unit vectors, precise for the small angles where the cosine itself rounds to 1.
+        0.5 * (d - self.sun).length_squared() <= sun_one_minus_cos()
+    }
+
+    fn sky(&self, cos_theta: f32, cos_gamma: f32) -> Vec3 {
+        // The zenith values scaled by the distribution's change from the zenith. Below the
+        // horizon the sky carries on with the horizon's colors.
+        let cos_theta = cos_theta.max(0.0);
+        let [big_y, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * perez_function(self.perez[i], cos_theta, cos_gamma)
+                / self.perez_zenith[i]
+        });
+        xyy_to_rgb(x, y, UNITS * big_y.max(0.0))
+    }
+}
+
+fn perez_function(c: [f32; 5], cos_theta: f32, cos_gamma: f32) -> f32 {
+    // Relative sky brightness at the angle theta from the zenith and gamma from the sun.
+    let gamma = cos_gamma.clamp(-1.0, 1.0).acos();
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+fn sun_radiance(theta_s: f32, turbidity: f32) -> Vec3 {
+    // The sunlight left after Rayleigh scattering by the air and scattering by aerosols on its
+    // way through the atmosphere, at a wavelength for each of red, green and blue, spread over
+    // the sun's disk.
+    let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |wavelength: f32| {
+        // In micrometers.
+        let rayleigh = 0.008735 * wavelength.powf(-4.08);
+        let aerosol = beta * wavelength.powf(-1.3);
+        (-(rayleigh + aerosol) * air_mass).exp()
+    };
+    let radiance = UNITS * SUN_ILLUMINANCE / sun_solid_angle();
+    radiance
+        * Vec3::new(
+            transmittance(0.65),
+            transmittance(0.55),
+            transmittance(0.45),
+        )
+}
+
+fn sun_one_minus_cos() -> f32 {
+    // Of the sun's angular radius, without the cancellation.
+    2.0 * (0.5 * SUN_RADIUS).sin().powi(2)
+}
+
+fn sun_solid_angle() -> f32 {
+    2.0 * PI * sun_one_minus_cos()
+}
+
+fn xyy_to_rgb(x: f32, y: f32, big_y: f32) -> Vec3 {
+    // From CIE chromaticity and luminance to linear sRGB.
+    if y <= 0.0 {
+        return Vec3::default();
+    }
+    let big_x = x * big_y / y;
+    let big_z = (1.0 - x - y) * big_y / y;
+    Vec3::new(
+        (3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z).max(0.0),
+    )
+}
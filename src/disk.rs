@@ -0,0 +1,112 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::rng::random;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+#[derive(Debug, Clone)]
+pub struct Disk {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub material: Material,
+    uvw: Onb, // Frame with w along the normal, giving the angle of hit points around it
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Material) -> Disk {
+        let uvw = Onb::new(normal);
+        Disk {
+            center,
+            normal: uvw.w,
+            radius,
+            material,
+            uvw,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // No hit if the ray is parallel to the plane.
+        let denom = Vec3::dot(&self.normal, &r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = Vec3::dot(&self.normal, &(self.center - r.origin())) / denom;
+        if (t <= ray_t.start) || (ray_t.end <= t) {
+            return None;
+        }
+
+        let p = r.at(t);
+        let offset = p - self.center;
+        let distance = offset.length();
+        if distance > self.radius {
+            return None;
+        }
+
+        // u: angle around the normal, v: distance from the center, both mapped to [0,1].
+        let phi = Vec3::dot(&offset, &self.uvw.v).atan2(Vec3::dot(&offset, &self.uvw.u));
+        let (front_face, normal) = face_normal(r, self.normal);
+
+        Some(HitRecord {
+            p,
+            normal,
+            material: &self.material,
+            t,
+            u: (phi + PI) / (2.0 * PI),
+            v: distance / self.radius,
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        disk_bbox(self.center, self.normal, self.radius).pad()
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        // Convert the uniform density over the area to a density over solid angle.
+        let ray_t = Range {
+            start: 0.001,
+            end: f32::INFINITY,
+        };
+        let Some(rec) = self.hit(&Ray::new(origin, direction), ray_t, 0) else {
+            return 0.0;
+        };
+
+        let area = PI * self.radius * self.radius;
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (Vec3::dot(&direction, &self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * area)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        // Uniform point on the disk.
+        let r = self.radius * random::<f32>().sqrt();
+        let phi = 2.0 * PI * random::<f32>();
+        let p = self.center
+            + self
+                .uvw
+                .transform(Vec3::new(r * phi.cos(), r * phi.sin(), 0.0));
+        p - origin
+    }
+}
+
+pub(crate) fn disk_bbox(center: Vec3, normal: Vec3, radius: f32) -> Aabb {
+    // The disk's extent along each axis shrinks as the unit normal tilts towards that axis.
+    let n = normal;
+    let extent = Vec3::new(
+        (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+        (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+        (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+    ) * radius;
+    Aabb::new(center - extent, center + extent)
+}
@@ -0,0 +1,155 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::path::Path;
+
+use crate::rng::random;
+use crate::vec3::Vec3;
+
+// Image of the radiance arriving from every direction around the scene, in the equirectangular
+// layout: longitude across and latitude down from straight up. The middle of the image is
+// towards -z.
+pub struct EnvironmentMap {
+    pub strength: f32, // Multiplier of the image's radiance
+    pub rotation: f32, // Turn around the y axis in radians
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,          // Linear radiance, row-major with the top row first
+    rows: Distribution,         // Of the rows' total weights
+    columns: Vec<Distribution>, // Of the pixels' weights within each row
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> EnvironmentMap {
+        assert_eq!(pixels.len(), width * height);
+        let mut map = EnvironmentMap {
+            strength: 1.0,
+            rotation: 0.0,
+            width,
+            height,
+            pixels,
+            rows: Distribution::default(),
+            columns: Vec::new(),
+        };
+
+        // Directions are sampled with a probability following the brightness, so a small sun
+        // gets most of the samples. A row's pixels cover less solid angle towards the poles.
+        map.columns = (0..height)
+            .map(|j| Distribution::new((0..width).map(|i| map.weight(i, j))))
+            .collect();
+        map.rows = Distribution::new(map.columns.iter().map(Distribution::total));
+        map
+    }
+
+    pub fn load(path: &Path) -> Result<EnvironmentMap, image::ImageError> {
+        // HDR and EXR files store linear radiance, unlike the gamma-encoded image textures.
+        let image = image::open(path)?.to_rgb32f();
+        let pixels = image
+            .pixels()
+            .map(|p| Vec3::new(p[0].max(0.0), p[1].max(0.0), p[2].max(0.0)))
+            .collect();
+
+        Ok(EnvironmentMap::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+        ))
+    }
+
+    pub fn value(&self, direction: Vec3) -> Vec3 {
+        let (i, j) = self.pixel(direction);
+        self.strength * self.pixels[j * self.width + i]
+    }
+
+    pub fn pdf_value(&self, direction: Vec3) -> f32 {
+        // The chosen pixel's probability spread over its area of the image, then over the
+        // solid angle the image stretches onto the sphere.
+        let total = self.rows.total();
+        let sin_theta = (1.0 - Vec3::unit_vector(direction).y().powi(2))
+            .max(0.0)
+            .sqrt();
+        if total <= 0.0 || sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (i, j) = self.pixel(direction);
+        let probability = self.weight(i, j) / total;
+        probability * (self.width * self.height) as f32 / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn random(&self) -> Vec3 {
+        // A random direction through a pixel picked by its weight.
+        let j = self.rows.sample(random());
+        let i = self.columns[j].sample(random());
+        let u = (i as f32 + random::<f32>()) / self.width as f32;
+        let v = (j as f32 + random::<f32>()) / self.height as f32;
+
+        let phi = 2.0 * PI * (u - 0.5) + self.rotation;
+        let theta = PI * v;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    fn pixel(&self, direction: Vec3) -> (usize, usize) {
+        // Column and row of the pixel seen in a direction.
+        let d = Vec3::unit_vector(direction);
+        let phi = d.x().atan2(-d.z()) - self.rotation;
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        let v = d.y().clamp(-1.0, 1.0).acos() / PI;
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+        (i, j)
+    }
+
+    fn weight(&self, i: usize, j: usize) -> f32 {
+        // The pixel's luminance times the solid angle of its row's pixels.
+        let p = self.pixels[j * self.width + i];
+        let luminance = 0.2126 * p.x() + 0.7152 * p.y() + 0.0722 * p.z();
+        let sin_theta = (PI * (j as f32 + 0.5) / self.height as f32).sin();
+        luminance * sin_theta
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("strength", &self.strength)
+            .field("rotation", &self.rotation)
+            .finish_non_exhaustive()
+    }
+}
+
+// Discrete distribution picking indices with probabilities proportional to their weights.
+#[derive(Default)]
+struct Distribution {
+    cdf: Vec<f32>, // Running totals of the weights, the last one the total
+}
+
+impl Distribution {
+    fn new(weights: impl Iterator<Item = f32>) -> Distribution {
+        let mut total = 0.0f64;
+        let cdf = weights
+            .map(|weight| {
+                total += weight as f64;
+                total as f32
+            })
+            .collect();
+        Distribution { cdf }
+    }
+
+    fn total(&self) -> f32 {
+        self.cdf.last().copied().unwrap_or(0.0)
+    }
+
+    fn sample(&self, xi: f32) -> usize {
+        // The first index whose running total passes xi of the total, which skips any with
+        // zero weight.
+        let target = xi * self.total();
+        self.cdf
+            .partition_point(|&c| c <= target)
+            .min(self.cdf.len() - 1)
+    }
+}
@@ -0,0 +1,30 @@
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Default)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Vec3>, // Linear radiance, row-major with the top scanline first
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Vec3::default(); width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn scanlines_mut(&mut self) -> std::slice::ChunksMut<'_, Vec3> {
+        self.pixels.chunks_mut(self.width)
+    }
+}
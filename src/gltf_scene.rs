@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use gltf::camera::Projection;
+use gltf::image::Format;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+
+use crate::camera::Camera;
+use crate::hittable_list::HittableList;
+use crate::mat4::Mat4;
+use crate::material::Material;
+use crate::mesh::{MeshError, MeshFace, TriangleMesh};
+use crate::scene::Scene;
+use crate::texture::{ImageTexture, SolidColor, Texture};
+use crate::vec3::Vec3;
+
+// A camera found in the node hierarchy, with its world transform.
+struct CameraNode {
+    transform: Mat4,
+    yfov: f32,
+    aspect_ratio: Option<f32>,
+}
+
+struct Loader {
+    buffers: Vec<gltf::buffer::Data>,
+    images: Vec<gltf::image::Data>,
+    materials: HashMap<Option<usize>, Material>,
+    camera: Option<CameraNode>,
+    world: HittableList,
+}
+
+// glTF 2.0 loader for .gltf files with embedded or relative buffers and images, and .glb files.
+// Every primitive becomes a mesh with the transforms of its nodes applied to the vertices. The
+// first perspective camera, if any, sets up `camera`.
+pub fn load_gltf(path: &Path, mut camera: Camera) -> Result<Scene, MeshError> {
+    let (document, buffers, images) = gltf::import(path).map_err(MeshError::Gltf)?;
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| MeshError::Parse("no scene".to_string()))?;
+
+    let mut loader = Loader {
+        buffers,
+        images,
+        materials: HashMap::new(),
+        camera: None,
+        world: HittableList::new(Vec::new()),
+    };
+    for node in scene.nodes() {
+        loader.node(&node, Mat4::identity())?;
+    }
+
+    if let Some(found) = loader.camera {
+        // glTF cameras look down their local -z axis, with +y up.
+        let transform = found.transform;
+        camera.lookfrom = transform.transform_point(Vec3::default());
+        camera.lookat = camera.lookfrom
+            - Vec3::unit_vector(transform.transform_vector(Vec3::new(0.0, 0.0, 1.0)));
+        camera.vup = transform.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+        camera.vfov = found.yfov.to_degrees();
+        if let Some(aspect_ratio) = found.aspect_ratio {
+            camera.aspect_ratio = aspect_ratio;
+        }
+    }
+
+    Ok(Scene {
+        camera,
+        world: loader.world,
+        lights: HittableList::new(Vec::new()),
+        delta_lights: Vec::new(),
+    })
+}
+
+impl Loader {
+    fn node(&mut self, node: &gltf::Node, parent: Mat4) -> Result<(), MeshError> {
+        let transform = parent * Mat4::from_columns(node.transform().matrix());
+
+        if let Some(camera) = node.camera() {
+            if let (None, Projection::Perspective(perspective)) =
+                (&self.camera, camera.projection())
+            {
+                self.camera = Some(CameraNode {
+                    transform,
+                    yfov: perspective.yfov(),
+                    aspect_ratio: perspective.aspect_ratio(),
+                });
+            }
+        }
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(mesh) = self.primitive(&primitive, &transform)? {
+                    self.world.add(Box::new(mesh));
+                }
+            }
+        }
+
+        for child in node.children() {
+            self.node(&child, transform)?;
+        }
+        Ok(())
+    }
+
+    fn primitive(
+        &mut self,
+        primitive: &gltf::Primitive,
+        transform: &Mat4,
+    ) -> Result<Option<TriangleMesh>, MeshError> {
+        // Points and lines have no surface to hit.
+        let mode = primitive.mode();
+        if !matches!(
+            mode,
+            Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan
+        ) {
+            return Ok(None);
+        }
+
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let positions: Vec<Vec3> = reader
+            .read_positions()
+            .ok_or_else(|| MeshError::Parse("primitive without positions".to_string()))?
+            .map(|p| transform.transform_point(Vec3::from(p)))
+            .collect();
+        let count = positions.len();
+
+        // Attributes are only kept when there's one per vertex.
+        let mut normals: Vec<Vec3> = reader.read_normals().map_or(Vec::new(), |normals| {
+            normals
+                .map(|n| transform.transform_normal(Vec3::from(n)))
+                .collect()
+        });
+        normals.truncate(if normals.len() == count { count } else { 0 });
+
+        // glTF puts the texture origin at the top left, the renderer at the bottom left.
+        let material = primitive.material();
+        let set = material
+            .pbr_metallic_roughness()
+            .base_color_texture()
+            .map_or(0, |info| info.tex_coord());
+        let mut uvs: Vec<(f32, f32)> = reader.read_tex_coords(set).map_or(Vec::new(), |uvs| {
+            uvs.into_f32().map(|[u, v]| (u, 1.0 - v)).collect()
+        });
+        uvs.truncate(if uvs.len() == count { count } else { 0 });
+
+        let mut colors: Vec<Vec3> = reader.read_colors(0).map_or(Vec::new(), |colors| {
+            colors.into_rgb_f32().map(Vec3::from).collect()
+        });
+        colors.truncate(if colors.len() == count { count } else { 0 });
+
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..count as u32).collect(),
+        };
+        if let Some(index) = indices.iter().find(|&&i| i as usize >= count) {
+            return Err(MeshError::Parse(format!(
+                "vertex index {} out of range",
+                index
+            )));
+        }
+
+        let triangles: Vec<[u32; 3]> = match mode {
+            Mode::TriangleStrip => (2..indices.len())
+                .map(|i| {
+                    // Every other triangle of a strip is wound the other way round.
+                    if i % 2 == 0 {
+                        [indices[i - 2], indices[i - 1], indices[i]]
+                    } else {
+                        [indices[i - 1], indices[i - 2], indices[i]]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => (2..indices.len())
+                .map(|i| [indices[0], indices[i - 1], indices[i]])
+                .collect(),
+            _ => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+        };
+        let faces = triangles
+            .into_iter()
+            .map(|vertices| MeshFace {
+                vertices,
+                normals: (!normals.is_empty()).then_some(vertices),
+                uvs: (!uvs.is_empty()).then_some(vertices),
+                material: 0,
+            })
+            .collect();
+
+        let material = self.material(&material);
+        let mesh = TriangleMesh::new(positions, normals, uvs, faces, vec![material]);
+        if colors.is_empty() {
+            Ok(Some(mesh))
+        } else {
+            Ok(Some(mesh.with_colors(colors)))
+        }
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> Material {
+        // Primitives without a material share the glTF default one, which has no index.
+        if let Some(built) = self.materials.get(&material.index()) {
+            return built.clone();
+        }
+        let built = self.build_material(material);
+        self.materials.insert(material.index(), built.clone());
+        built
+    }
+
+    fn build_material(&self, material: &gltf::Material) -> Material {
+        let max = |c: Vec3| c.x().max(c.y()).max(c.z());
+        let pbr = material.pbr_metallic_roughness();
+
+        // Metallic-roughness materials mapped onto the closest of the renderer's materials:
+        // emitters first, then transmissive or blended materials as glass, then mostly
+        // metallic ones as metal with the roughness as fuzz, and everything else as diffuse.
+        let emit =
+            material.emissive_strength().unwrap_or(1.0) * Vec3::from(material.emissive_factor());
+        if max(emit) > 0.0 {
+            return Material::DiffuseLight {
+                emit,
+                two_sided: material.double_sided(),
+            };
+        }
+
+        let [r, g, b, alpha] = pbr.base_color_factor();
+        let transmission = material
+            .transmission()
+            .map_or(0.0, |t| t.transmission_factor());
+        if transmission > 0.0 || (material.alpha_mode() == AlphaMode::Blend && alpha < 1.0) {
+            return Material::Dielectric {
+                ir: material.ior().unwrap_or(1.5),
+            };
+        }
+
+        let factor = Vec3::new(r, g, b);
+        let albedo: Arc<dyn Texture> = match pbr.base_color_texture() {
+            Some(info) => {
+                let image = &self.images[info.texture().source().index()];
+                Arc::new(image_texture(image, factor))
+            }
+            None => Arc::new(SolidColor::new(factor)),
+        };
+        if pbr.metallic_factor() >= 0.5 {
+            Material::Metal {
+                albedo,
+                fuzz: pbr.roughness_factor().clamp(0.0, 1.0),
+            }
+        } else {
+            Material::Lambertian { albedo }
+        }
+    }
+}
+
+fn image_texture(image: &gltf::image::Data, factor: Vec3) -> ImageTexture {
+    // Decoded glTF images are 8 or 16 bit sRGB, or linear float. Gray images repeat their one
+    // channel, and alpha is dropped.
+    let (channels, size) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes: &[u8]| match size {
+        1 => {
+            let c = bytes[0] as f32 / 255.0;
+            c * c
+        }
+        2 => {
+            let c = u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0;
+            c * c
+        }
+        _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    };
+
+    let pixels = image
+        .pixels
+        .chunks_exact(channels * size)
+        .map(|pixel| {
+            let r = channel(&pixel[..size]);
+            let color = if channels < 3 {
+                Vec3::new(r, r, r)
+            } else {
+                Vec3::new(r, channel(&pixel[size..]), channel(&pixel[2 * size..]))
+            };
+            factor * color
+        })
+        .collect();
+    ImageTexture::new(image.width as usize, image.height as usize, pixels)
+}
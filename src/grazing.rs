@@ -0,0 +1,72 @@
+use crate::hittable::Hittable;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// Checks for the shapes' tests with rays grazing their surfaces, where the roots of the
+// intersection equations meet and lose their precision. `distance` is how far a point is from
+// the shape's surface, which hits must be on.
+
+// Distances of the rays' origins from the shapes, near and far.
+const FAR: [f32; 3] = [2.0, 10.0, 1000.0];
+
+fn assert_hits_surface(shape: &dyn Hittable, distance: &dyn Fn(Vec3) -> f32, r: &Ray) {
+    let rec = shape
+        .hit(r, 0.001..f32::INFINITY, 0)
+        .unwrap_or_else(|| panic!("missed along {r:?}"));
+    assert!(rec.t.is_finite() && rec.u.is_finite() && rec.v.is_finite());
+    assert!(
+        (rec.normal.length() - 1.0).abs() < 1e-4,
+        "normal {:?}",
+        rec.normal
+    );
+    assert!(
+        distance(rec.p).abs() < 1e-3,
+        "off the surface at {:?}",
+        rec.p
+    );
+}
+
+fn assert_misses(shape: &dyn Hittable, r: &Ray) {
+    let rec = shape.hit(r, 0.001..f32::INFINITY, 0);
+    assert!(rec.is_none(), "hit {rec:?} along {r:?}");
+}
+
+pub fn check_tangent(
+    shape: &dyn Hittable,
+    distance: &dyn Fn(Vec3) -> f32,
+    ray: &dyn Fn(f32, f32) -> Ray,
+) {
+    // `ray(offset, far)` starts far from the shape on a line offset from a tangent to the
+    // surface, outside for positive offsets. Rays just inside must hit the surface and those
+    // just outside miss it, while the tangent itself may do either.
+    for far in FAR {
+        assert_misses(shape, &ray(1e-3, far));
+        assert_hits_surface(shape, distance, &ray(-1e-3, far));
+        if let Some(rec) = shape.hit(&ray(0.0, far), 0.001..f32::INFINITY, 0) {
+            assert!(
+                distance(rec.p).abs() < 1e-3,
+                "off the surface at {:?}",
+                rec.p
+            );
+        }
+    }
+}
+
+pub fn check_through(
+    shape: &dyn Hittable,
+    distance: &dyn Fn(Vec3) -> f32,
+    ray: &dyn Fn(f32) -> Ray,
+) {
+    // `ray(far)` starts far from the shape along a line that crosses the surface, however
+    // shallowly, so it must hit it.
+    for far in FAR {
+        assert_hits_surface(shape, distance, &ray(far));
+    }
+}
+
+pub fn check_clear(shape: &dyn Hittable, ray: &dyn Fn(f32) -> Ray) {
+    // `ray(far)` starts far from the shape along a line that passes it by.
+    for far in FAR {
+        assert_misses(shape, &ray(far));
+    }
+}
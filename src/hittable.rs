@@ -1,19 +1,78 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::Vec3;
 use std::ops::Range;
+use std::sync::Arc;
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct HitRecord {
+#[derive(Debug, Clone, Copy)]
+pub struct HitRecord<'a> {
     pub p: Vec3,
     pub normal: Vec3,
-    pub material: Material,
+    pub material: &'a Material,
     pub t: f32,
+    pub u: f32, // Surface coordinates of the hit point
+    pub v: f32,
     pub front_face: bool,
+    pub color: Option<Vec3>, // Interpolated vertex color, replaces the albedo of diffuse materials
 }
 
-pub trait Hittable {
-    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord> {
+pub fn face_normal(r: &Ray, outward_normal: Vec3) -> (bool, Vec3) {
+    // Returns whether the ray hits the front face, and the normal pointing against the ray.
+    // NOTE: the parameter `outward_normal` is assumed to have unit length.
+    let front_face = Vec3::dot(&r.direction(), &outward_normal) < 0.0;
+    let normal = if front_face {
+        outward_normal
+    } else {
+        -outward_normal
+    };
+    (front_face, normal)
+}
+
+pub trait Hittable: Send + Sync {
+    fn hit(&self, _r: &Ray, _ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
         None
     }
+
+    fn bounding_box(&self) -> Aabb;
+
+    // Light sampling: the solid angle density of `random` directions from `origin` towards the
+    // object, and a random vector from `origin` to a point on the object. Objects that are never
+    // used as lights keep the defaults.
+    fn pdf_value(&self, _origin: Vec3, _direction: Vec3) -> f32 {
+        0.0
+    }
+
+    fn random(&self, _origin: Vec3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    // Participating media: the fraction of light crossing the object's volume along the ray
+    // within `ray_t` without scattering. Surfaces are solid, and let everything else through.
+    fn transmittance(&self, _r: &Ray, _ray_t: Range<f32>, _depth: i32) -> f32 {
+        1.0
+    }
+}
+
+// A shared object, like one in both the world and the list of lights.
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
+        (**self).hit(r, ray_t, depth)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        (**self).random(origin)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> f32 {
+        (**self).transmittance(r, ray_t, depth)
+    }
 }
@@ -1,11 +1,14 @@
 use std::ops::Range;
 
+use crate::aabb::Aabb;
 use crate::hittable::*;
 use crate::ray::Ray;
+use crate::rng::random_range;
+use crate::vec3::Vec3;
 
 #[derive(Default)]
 pub struct HittableList {
-    objects: Vec<Box<dyn Hittable>>,
+    pub(crate) objects: Vec<Box<dyn Hittable>>,
 }
 
 impl HittableList {
@@ -16,16 +19,20 @@ impl HittableList {
     pub fn add(&mut self, object: Box<dyn Hittable>) {
         self.objects.push(object);
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
         let mut hit_record = None;
         let mut closest_so_far: f32 = ray_t.end;
 
         for object in &self.objects {
             if let Some(rec) = object.hit(
-                &r,
+                r,
                 Range {
                     start: ray_t.start,
                     end: closest_so_far,
@@ -36,6 +43,32 @@ impl Hittable for HittableList {
                 hit_record = Some(rec)
             }
         }
-        return hit_record;
+        hit_record
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::default(), |bbox, object| {
+            Aabb::surrounding(&bbox, &object.bounding_box())
+        })
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        // Each object is picked with equal probability by `random`.
+        let weight = 1.0 / self.objects.len() as f32;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        self.objects[random_range(0..self.objects.len())].random(origin)
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> f32 {
+        self.objects
+            .iter()
+            .map(|object| object.transmittance(r, ray_t.clone(), depth))
+            .product()
     }
 }
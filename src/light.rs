@@ -0,0 +1,127 @@
+use crate::vec3::Vec3;
+
+// Light arriving at a point from a delta light.
+pub struct Illumination {
+    pub direction: Vec3,  // Unit vector towards the light
+    pub distance: f32,    // To the light along the direction, infinite for directional lights
+    pub irradiance: Vec3, // On a surface facing the light
+}
+
+// Lights concentrated in a single point or direction. Rays scattered at random never hit them,
+// so the integrator only finds them with shadow rays.
+pub trait Light: Send + Sync {
+    // None where the light doesn't reach, like outside a spotlight's cone.
+    fn illuminate(&self, p: Vec3) -> Option<Illumination>;
+}
+
+// Light shining from a point equally in all directions, falling off with the inverse square of
+// the distance.
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub intensity: Vec3, // Irradiance at a distance of 1
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Vec3) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn illuminate(&self, p: Vec3) -> Option<Illumination> {
+        let offset = self.position - p;
+        let distance = offset.length();
+        Some(Illumination {
+            direction: offset / distance,
+            distance,
+            irradiance: self.intensity / (distance * distance),
+        })
+    }
+}
+
+// Point light shining only within a cone around its direction. The light fades out smoothly
+// between the inner and outer angles from the axis.
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub intensity: Vec3, // Irradiance at a distance of 1 inside the inner cone
+    axis: Vec3,
+    cos_inner: f32,
+    cos_outer: f32,
+}
+
+impl SpotLight {
+    // Angles in radians.
+    pub fn new(
+        position: Vec3,
+        direction: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+        intensity: Vec3,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            intensity,
+            axis: Vec3::unit_vector(direction),
+            cos_inner: inner_angle.min(outer_angle).cos(),
+            cos_outer: outer_angle.cos(),
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn illuminate(&self, p: Vec3) -> Option<Illumination> {
+        let offset = self.position - p;
+        let distance = offset.length();
+        let direction = offset / distance;
+
+        let cos_theta = -Vec3::dot(&direction, &self.axis);
+        if cos_theta <= self.cos_outer {
+            return None;
+        }
+        let falloff = if cos_theta >= self.cos_inner {
+            1.0
+        } else {
+            // Smoothstep over the cosine.
+            let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        };
+
+        Some(Illumination {
+            direction,
+            distance,
+            irradiance: falloff * self.intensity / (distance * distance),
+        })
+    }
+}
+
+// Light arriving from a single direction everywhere in the scene, like sunlight.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    pub irradiance: Vec3,
+    towards: Vec3, // Unit vector against the light's direction
+}
+
+impl DirectionalLight {
+    // `direction` is the way the light travels.
+    pub fn new(direction: Vec3, irradiance: Vec3) -> DirectionalLight {
+        DirectionalLight {
+            irradiance,
+            towards: -Vec3::unit_vector(direction),
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn illuminate(&self, _p: Vec3) -> Option<Illumination> {
+        Some(Illumination {
+            direction: self.towards,
+            distance: f32::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
@@ -1,26 +1,113 @@
+use bvh::BvhNode;
 use camera::Camera;
+use clap::Parser;
+use cli::Args;
 use hittable_list::*;
 use material::Material;
-use rand::random;
+use output::{write_image, ImageFormat};
 use ray::Ray;
+use rng::random;
+use scene::{load_scene, Scene};
 use sphere::*;
+use std::process;
+use std::sync::Arc;
+use texture::SolidColor;
 use vec3::Vec3;
 
+pub mod aabb;
+pub mod background;
+pub mod bvh;
 pub mod camera;
+pub mod capsule;
+pub mod cli;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod daylight;
+pub mod disk;
+pub mod environment;
+pub mod framebuffer;
+pub mod gltf_scene;
+#[cfg(test)]
+mod grazing;
 pub mod hittable;
 pub mod hittable_list;
+pub mod light;
+pub mod mat4;
 pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod microfacet;
+pub mod moving_sphere;
+pub mod obj;
+pub mod onb;
+pub mod output;
+pub mod perlin;
+pub mod ply;
+pub mod quad;
 pub mod ray;
+pub mod rng;
+pub mod scene;
+pub mod sdf;
 pub mod sphere;
+pub mod texture;
+pub mod torus;
+pub mod transform;
+pub mod triangle;
 pub mod utils;
 pub mod vec3;
+pub mod volume;
 
 fn main() {
+    let args = Args::parse();
+
+    // Building the scene draws random numbers too, for the random spheres and the noise
+    // textures' lattices, so the seed applies from here.
+    if let Some(seed) = args.seed {
+        rng::seed(seed);
+    }
+
+    // Load the scene file, or fall back to the random spheres scene.
+    let Scene {
+        mut camera,
+        world,
+        lights,
+        delta_lights,
+    } = match &args.scene {
+        Some(path) => load_scene(path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            process::exit(1);
+        }),
+        None => random_spheres(),
+    };
+    args.apply(&mut camera);
+    let world = BvhNode::new(world);
+
+    let format = args
+        .format
+        .or_else(|| ImageFormat::from_path(&args.output))
+        .unwrap_or_else(|| {
+            eprintln!(
+                "{}: unknown image format, use --format to choose one",
+                args.output.display()
+            );
+            process::exit(1);
+        });
+
+    let image = camera.render(&world, &lights, &delta_lights);
+    write_image(&image, &args.output, format).unwrap_or_else(|err| {
+        eprintln!("{}: {}", args.output.display(), err);
+        process::exit(1);
+    });
+}
+
+fn random_spheres() -> Scene {
     // World
     let mut world = HittableList::default();
 
     let ground_material = Material::Lambertian {
-        albedo: Vec3::new(0.5, 0.5, 0.5),
+        albedo: Arc::new(SolidColor::new(Vec3::new(0.5, 0.5, 0.5))),
     };
     world.add(Box::new(Sphere::new(
         Vec3::new(0.0, -1000.0, 0.0),
@@ -43,15 +130,17 @@ fn main() {
                 if chose_mat < 0.8 {
                     //difuse
                     let albedo = Vec3::random(0.0, 1.0) * Vec3::random(0.0, 1.0);
-                    _sphere_material = Material::Lambertian { albedo: albedo };
+                    _sphere_material = Material::Lambertian {
+                        albedo: Arc::new(SolidColor::new(albedo)),
+                    };
                     world.add(Box::new(Sphere::new(center, 0.2, _sphere_material)));
                 } else if chose_mat < 0.95 {
                     //metal
                     let albedo = Vec3::random(0.5, 1.0);
                     let fuzz = random::<f32>();
                     _sphere_material = Material::Metal {
-                        albedo: albedo,
-                        fuzz: fuzz,
+                        albedo: Arc::new(SolidColor::new(albedo)),
+                        fuzz,
                     };
                     world.add(Box::new(Sphere::new(center, 0.2, _sphere_material)));
                 } else {
@@ -71,7 +160,7 @@ fn main() {
     )));
 
     let material2 = Material::Lambertian {
-        albedo: Vec3::new(0.4, 0.2, 0.1),
+        albedo: Arc::new(SolidColor::new(Vec3::new(0.4, 0.2, 0.1))),
     };
     world.add(Box::new(Sphere::new(
         Vec3::new(-4.0, 1.0, 0.0),
@@ -80,7 +169,7 @@ fn main() {
     )));
 
     let material3 = Material::Metal {
-        albedo: Vec3::new(0.7, 0.6, 0.5),
+        albedo: Arc::new(SolidColor::new(Vec3::new(0.7, 0.6, 0.5))),
         fuzz: 0.0,
     };
     world.add(Box::new(Sphere::new(
@@ -96,7 +185,7 @@ fn main() {
     cam.samples_per_pixel = 500;
     cam.max_deph = 50;
 
-    cam.vfov = -20.0;
+    cam.vfov = 20.0;
     cam.lookfrom = Vec3::new(13.0, 2.0, 3.0);
     cam.lookat = Vec3::new(0.0, 0.0, 0.0);
     cam.vup = Vec3::new(0.0, 1.0, 0.0);
@@ -104,5 +193,10 @@ fn main() {
     cam.defocus_angle = 0.6;
     cam.focus_dist = 10.0;
 
-    cam.render(&world)
+    Scene {
+        camera: cam,
+        world,
+        lights: HittableList::default(),
+        delta_lights: Vec::new(),
+    }
 }
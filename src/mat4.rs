@@ -0,0 +1,165 @@
+use std::ops;
+
+use crate::vec3::Vec3;
+
+// Affine 4x4 matrix, stored row by row. The bottom row is assumed to be (0, 0, 0, 1), so
+// points get the translation in the last column and vectors don't.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub e: [[f32; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::identity()
+    }
+}
+
+impl Mat4 {
+    pub fn new(rows: [[f32; 4]; 4]) -> Mat4 {
+        Mat4 { e: rows }
+    }
+
+    pub fn from_columns(columns: [[f32; 4]; 4]) -> Mat4 {
+        let mut e = [[0.0; 4]; 4];
+        for (i, column) in columns.iter().enumerate() {
+            for (j, value) in column.iter().enumerate() {
+                e[j][i] = *value;
+            }
+        }
+        Mat4 { e }
+    }
+
+    pub fn identity() -> Mat4 {
+        Mat4::scaling(Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        let mut m = Mat4::identity();
+        for axis in 0..3 {
+            m.e[axis][3] = offset[axis];
+        }
+        m
+    }
+
+    pub fn scaling(factors: Vec3) -> Mat4 {
+        let mut e = [[0.0; 4]; 4];
+        for axis in 0..3 {
+            e[axis][axis] = factors[axis];
+        }
+        e[3][3] = 1.0;
+        Mat4 { e }
+    }
+
+    // Rotations by an angle in degrees, counterclockwise when looking down the axis towards the
+    // origin.
+    pub fn rotation_x(degrees: f32) -> Mat4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_y(degrees: f32) -> Mat4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_z(degrees: f32) -> Mat4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Mat4::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        // Only stays affine for matrices without a translation.
+        let mut e = [[0.0; 4]; 4];
+        for (i, row) in e.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.e[j][i];
+            }
+        }
+        Mat4 { e }
+    }
+
+    fn column(&self, i: usize) -> Vec3 {
+        Vec3::new(self.e[0][i], self.e[1][i], self.e[2][i])
+    }
+
+    pub fn determinant(&self) -> f32 {
+        // Of the linear part, the only part an affine matrix's determinant depends on.
+        let (c0, c1, c2) = (self.column(0), self.column(1), self.column(2));
+        Vec3::dot(&c0, &Vec3::cross(&c1, &c2))
+    }
+
+    pub fn inverse(&self) -> Option<Mat4> {
+        // The inverse linear part has the cross products of the columns as its rows, over the
+        // determinant. The translation is then undone with it.
+        let det = self.determinant();
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let (c0, c1, c2) = (self.column(0), self.column(1), self.column(2));
+        let rows = [
+            Vec3::cross(&c1, &c2) / det,
+            Vec3::cross(&c2, &c0) / det,
+            Vec3::cross(&c0, &c1) / det,
+        ];
+        let offset = self.column(3);
+
+        let mut e = [[0.0; 4]; 4];
+        for (i, row) in rows.iter().enumerate() {
+            e[i] = [row.x(), row.y(), row.z(), -Vec3::dot(row, &offset)];
+        }
+        e[3][3] = 1.0;
+        Some(Mat4 { e })
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        self.transform_vector(p) + self.column(3)
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        v.x() * self.column(0) + v.y() * self.column(1) + v.z() * self.column(2)
+    }
+
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        // Normals transform by the inverse transpose. The cofactor matrix is that times the
+        // determinant, so it gives the same direction, flipped back when the determinant is
+        // negative, without needing an inverse. The result is not normalized.
+        let (c0, c1, c2) = (self.column(0), self.column(1), self.column(2));
+        let (n0, n1, n2) = (
+            Vec3::cross(&c1, &c2),
+            Vec3::cross(&c2, &c0),
+            Vec3::cross(&c0, &c1),
+        );
+        let sign = Vec3::dot(&c0, &n0).signum();
+        sign * (n.x() * n0 + n.y() * n1 + n.z() * n2)
+    }
+}
+
+impl ops::Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut e = [[0.0; 4]; 4];
+        for (i, row) in e.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.e[i][k] * other.e[k][j]).sum();
+            }
+        }
+        Mat4 { e }
+    }
+}
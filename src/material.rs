@@ -1,20 +1,30 @@
+use crate::microfacet::*;
+use crate::onb::Onb;
+use crate::rng::random;
 use crate::utils::reflectance;
-use rand::random;
+use std::f32::consts::PI;
 use std::ops::Neg;
+use std::sync::Arc;
 
+use crate::texture::{SolidColor, Texture};
 use crate::{hittable::*, ray::Ray, vec3::Vec3};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Material {
-    Lambertian { albedo: Vec3 },
-    Metal { albedo: Vec3, fuzz: f32 },
+    Lambertian { albedo: Arc<dyn Texture> },
+    Metal { albedo: Arc<dyn Texture>, fuzz: f32 },
     Dielectric { ir: f32 },
+    Conductor { eta: Vec3, k: Vec3, roughness: f32 }, // Rough metal, refractive index eta + ik
+    RoughDielectric { ir: f32, roughness: f32 },      // Frosted glass
+    DiffuseLight { emit: Vec3, two_sided: bool },
+    Isotropic { albedo: Arc<dyn Texture> }, // Phase function of a participating medium
+    HenyeyGreenstein { albedo: Arc<dyn Texture>, g: f32 }, // Anisotropic phase function
 }
 
 impl Default for Material {
     fn default() -> Self {
         Self::Lambertian {
-            albedo: Vec3::default(),
+            albedo: Arc::new(SolidColor::new(Vec3::default())),
         }
     }
 }
@@ -26,20 +36,30 @@ pub fn scatter(
     attenuation: &mut Vec3,
     scattered: &mut Ray,
 ) -> bool {
-    match material {
-        &Material::Lambertian { albedo } => {
-            let target = rec.p + rec.normal + Vec3::random_in_unit_sphere();
-            *scattered = Ray::ray(rec.p, target - rec.p);
-            *attenuation = albedo;
-            return true;
-        }
-        &Material::Metal { albedo, fuzz } => {
+    match *material {
+        Material::Lambertian { ref albedo } => {
+            // Cosine-weighted scattering, with density cos(theta) / pi.
+            let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+
+            // Catch degenerate scatter direction
+            if scatter_direction.near_zero() {
+                scatter_direction = rec.normal;
+            }
+            *scattered = Ray::with_time(rec.p, scatter_direction, r_in.time());
+            *attenuation = surface_albedo(albedo, rec);
+            true
+        }
+        Material::Metal { ref albedo, fuzz } => {
             let reflected = Vec3::reflect(Vec3::unit_vector(r_in.direction()), rec.normal);
-            *scattered = Ray::ray(rec.p, reflected + fuzz * Vec3::random_unit_vector());
-            *attenuation = albedo;
-            return Vec3::dot(&scattered.direction(), &rec.normal) > 0.0;
+            *scattered = Ray::with_time(
+                rec.p,
+                reflected + fuzz * Vec3::random_unit_vector(),
+                r_in.time(),
+            );
+            *attenuation = albedo.value(rec.u, rec.v, rec.p);
+            Vec3::dot(&scattered.direction(), &rec.normal) > 0.0
         }
-        &Material::Dielectric { ir } => {
+        Material::Dielectric { ir } => {
             *attenuation = Vec3::new(1.0, 1.0, 1.0);
             let refraction_ratio = if rec.front_face { ir.recip() } else { ir };
 
@@ -55,8 +75,129 @@ pub fn scatter(
                 Vec3::refract(unit_direction, rec.normal, refraction_ratio)
             };
 
-            *scattered = Ray::ray(rec.p, direction);
-            return true;
+            *scattered = Ray::with_time(rec.p, direction, r_in.time());
+            true
+        }
+        Material::Conductor { eta, k, roughness } => {
+            let uvw = Onb::new(rec.normal);
+            let wo = uvw.local(-Vec3::unit_vector(r_in.direction()));
+            let Some((wi, weight)) = conductor_sample(wo, eta, k, alpha(roughness)) else {
+                return false;
+            };
+            *scattered = Ray::with_time(rec.p, uvw.transform(wi), r_in.time());
+            *attenuation = weight;
+            true
         }
+        Material::RoughDielectric { ir, roughness } => {
+            let uvw = Onb::new(rec.normal);
+            let wo = uvw.local(-Vec3::unit_vector(r_in.direction()));
+            let eta = if rec.front_face { ir } else { ir.recip() };
+            let Some((wi, weight)) = dielectric_sample(wo, eta, alpha(roughness)) else {
+                return false;
+            };
+            *scattered = Ray::with_time(rec.p, uvw.transform(wi), r_in.time());
+            *attenuation = Vec3::new(weight, weight, weight);
+            true
+        }
+        Material::DiffuseLight { .. } => false,
+        Material::Isotropic { ref albedo } => {
+            // Scatter uniformly over the sphere of directions.
+            *scattered = Ray::with_time(rec.p, Vec3::random_unit_vector(), r_in.time());
+            *attenuation = albedo.value(rec.u, rec.v, rec.p);
+            true
+        }
+        Material::HenyeyGreenstein { ref albedo, g } => {
+            // Invert the phase function's distribution of the cosine to the incoming direction,
+            // forward for positive g and backward for negative g.
+            let xi = random::<f32>();
+            let cos_theta = if g.abs() < 1e-3 {
+                1.0 - 2.0 * xi
+            } else {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+                ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+            };
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * random::<f32>();
+
+            let uvw = Onb::new(r_in.direction());
+            let direction = uvw.transform(Vec3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ));
+            *scattered = Ray::with_time(rec.p, direction, r_in.time());
+            *attenuation = albedo.value(rec.u, rec.v, rec.p);
+            true
+        }
+    }
+}
+
+pub fn scattering(
+    material: &Material,
+    r_in: &Ray,
+    rec: &HitRecord,
+    direction: Vec3,
+) -> Option<(Vec3, f32)> {
+    // For the materials the integrator samples lights for: the fraction of the light arriving
+    // from `direction` that scatters back along the ray, which is the BSDF times the cosine or
+    // the phase function, and the density of `scatter` picking that direction.
+    match *material {
+        Material::Lambertian { ref albedo } => {
+            let cosine = Vec3::dot(&Vec3::unit_vector(direction), &rec.normal);
+            let pdf = (cosine / PI).max(0.0);
+            Some((pdf * surface_albedo(albedo, rec), pdf))
+        }
+        Material::Isotropic { ref albedo } => {
+            let pdf = 1.0 / (4.0 * PI);
+            Some((pdf * albedo.value(rec.u, rec.v, rec.p), pdf))
+        }
+        Material::HenyeyGreenstein { ref albedo, g } => {
+            let cos_theta = Vec3::dot(
+                &Vec3::unit_vector(r_in.direction()),
+                &Vec3::unit_vector(direction),
+            );
+            let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+            let pdf = (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt());
+            Some((pdf * albedo.value(rec.u, rec.v, rec.p), pdf))
+        }
+        Material::Conductor { eta, k, roughness } => {
+            let uvw = Onb::new(rec.normal);
+            let wo = uvw.local(-Vec3::unit_vector(r_in.direction()));
+            let wi = uvw.local(Vec3::unit_vector(direction));
+            Some(conductor_eval(wo, wi, eta, k, alpha(roughness)))
+        }
+        Material::RoughDielectric { ir, roughness } => {
+            let uvw = Onb::new(rec.normal);
+            let wo = uvw.local(-Vec3::unit_vector(r_in.direction()));
+            let wi = uvw.local(Vec3::unit_vector(direction));
+            let eta = if rec.front_face { ir } else { ir.recip() };
+            let (value, pdf) = dielectric_eval(wo, wi, eta, alpha(roughness));
+            Some((Vec3::new(value, value, value), pdf))
+        }
+        _ => None,
+    }
+}
+
+fn surface_albedo(albedo: &Arc<dyn Texture>, rec: &HitRecord) -> Vec3 {
+    // Vertex colors take the place of the texture.
+    rec.color
+        .unwrap_or_else(|| albedo.value(rec.u, rec.v, rec.p))
+}
+
+pub fn is_phase_function(material: &Material) -> bool {
+    // Materials of participating media, whose hits are scattering events inside a volume rather
+    // than surfaces.
+    matches!(
+        *material,
+        Material::Isotropic { .. } | Material::HenyeyGreenstein { .. }
+    )
+}
+
+pub fn emitted(material: &Material, rec: &HitRecord) -> Vec3 {
+    // Radiance emitted from the hit point towards the incoming ray. One-sided lights only emit
+    // from the side their outward normal points to.
+    match *material {
+        Material::DiffuseLight { emit, two_sided } if two_sided || rec.front_face => emit,
+        _ => Vec3::default(),
     }
 }
@@ -0,0 +1,113 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::rng::random;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// A volume of constant density inside a closed boundary, like smoke. Rays passing through it
+// scatter at exponentially distributed distances, the thicker the medium the sooner. The boundary
+// must be convex, rays are assumed to enter and leave it once.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: f32,
+    phase_function: Material,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Box<dyn Hittable>, density: f32, albedo: Arc<dyn Texture>) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -density.recip(),
+            phase_function: Material::Isotropic { albedo },
+        }
+    }
+}
+
+impl ConstantMedium {
+    fn inside(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<Range<f32>> {
+        // Where the ray's line enters and leaves the boundary, which may be behind the ray's
+        // origin when it starts inside the medium.
+        let everywhere = Range {
+            start: f32::NEG_INFINITY,
+            end: f32::INFINITY,
+        };
+        let enter = self.boundary.hit(r, everywhere, depth)?;
+        let after_enter = Range {
+            start: enter.t + 0.0001,
+            end: f32::INFINITY,
+        };
+        let leave = self.boundary.hit(r, after_enter, depth)?;
+
+        let t_enter = enter.t.max(ray_t.start).max(0.0);
+        let t_leave = leave.t.min(ray_t.end);
+        (t_enter < t_leave).then_some(t_enter..t_leave)
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
+        let inside = self.inside(r, ray_t, depth)?;
+
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (inside.end - inside.start) * ray_length;
+        let hit_distance = self.neg_inv_density * (1.0 - random::<f32>()).ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = inside.start + hit_distance / ray_length;
+        Some(HitRecord {
+            p: r.at(t),
+            normal: Vec3::new(1.0, 0.0, 0.0), // Arbitrary, media have no surface
+            material: &self.phase_function,
+            t,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> f32 {
+        match self.inside(r, ray_t, depth) {
+            Some(inside) => {
+                let distance = (inside.end - inside.start) * r.direction().length();
+                (distance / self.neg_inv_density).exp()
+            }
+            None => 1.0,
+        }
+    }
+}
+
+// Homogeneous fog filling the space between the scene's surfaces. Like a constant medium with
+// an isotropic phase function, but everywhere.
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub density: f32, // Chance of scattering per unit of distance
+    pub albedo: Vec3, // Fraction of light kept when scattering
+}
+
+impl Fog {
+    pub fn new(density: f32, albedo: Vec3) -> Fog {
+        Fog { density, albedo }
+    }
+
+    pub fn sample_distance(&self) -> f32 {
+        // Distance a ray travels before scattering, infinite without any fog.
+        -(1.0 - random::<f32>()).ln() / self.density
+    }
+
+    pub fn transmittance(&self, distance: f32) -> f32 {
+        // Fraction of light that crosses the distance without scattering.
+        (-self.density * distance).exp()
+    }
+}
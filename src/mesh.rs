@@ -0,0 +1,268 @@
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::triangle::intersect_triangle;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Maximum number of faces in a leaf of the mesh BVH.
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug)]
+pub enum MeshError {
+    Io(io::Error),
+    Parse(String),
+    Image(PathBuf, image::ImageError),
+    Gltf(gltf::Error),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::Io(err) => write!(f, "{}", err),
+            MeshError::Parse(message) => write!(f, "{}", message),
+            MeshError::Image(path, err) => write!(f, "could not load {}: {}", path.display(), err),
+            MeshError::Gltf(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl From<io::Error> for MeshError {
+    fn from(err: io::Error) -> Self {
+        MeshError::Io(err)
+    }
+}
+
+// A triangle, as indices into the mesh's shared buffers.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshFace {
+    pub vertices: [u32; 3],
+    pub normals: Option<[u32; 3]>, // Per-vertex normals for smooth shading
+    pub uvs: Option<[u32; 3]>,
+    pub material: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MeshNode {
+    bbox: Aabb,
+    start: u32, // First face of a leaf
+    count: u32, // Number of faces in a leaf, 0 for interior nodes
+    right: u32, // Right child of an interior node, the left child directly follows it
+}
+
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    colors: Vec<Vec3>, // Per-vertex colors, parallel to `positions`, or empty
+    faces: Vec<MeshFace>,
+    materials: Vec<Material>,
+    nodes: Vec<MeshNode>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f32, f32)>,
+        mut faces: Vec<MeshFace>,
+        materials: Vec<Material>,
+    ) -> TriangleMesh {
+        // Faces get reordered so that every BVH leaf covers a contiguous range of them.
+        let mut nodes = Vec::with_capacity(2 * faces.len() / LEAF_SIZE + 1);
+        build_nodes(&mut nodes, &positions, &mut faces, 0);
+
+        TriangleMesh {
+            positions,
+            normals,
+            uvs,
+            colors: Vec::new(),
+            faces,
+            materials,
+            nodes,
+        }
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Vec3>) -> TriangleMesh {
+        // Vertex colors replace the albedo of the mesh's Lambertian materials.
+        assert_eq!(colors.len(), self.positions.len());
+        self.colors = colors;
+        self
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    fn corners(&self, face: &MeshFace) -> (Vec3, Vec3, Vec3) {
+        let [a, b, c] = face.vertices;
+        (
+            self.positions[a as usize],
+            self.positions[b as usize],
+            self.positions[c as usize],
+        )
+    }
+}
+
+fn face_bbox(positions: &[Vec3], face: &MeshFace) -> Aabb {
+    let [a, b, c] = face.vertices.map(|i| positions[i as usize]);
+    Aabb::surrounding(&Aabb::new(a, b), &Aabb::new(c, c))
+}
+
+fn build_nodes(
+    nodes: &mut Vec<MeshNode>,
+    positions: &[Vec3],
+    faces: &mut [MeshFace],
+    start: usize,
+) {
+    let bbox = faces.iter().fold(Aabb::default(), |bbox, face| {
+        Aabb::surrounding(&bbox, &face_bbox(positions, face))
+    });
+    let index = nodes.len();
+    nodes.push(MeshNode {
+        bbox: bbox.pad(),
+        start: start as u32,
+        count: 0,
+        right: 0,
+    });
+
+    if faces.len() <= LEAF_SIZE {
+        nodes[index].count = faces.len() as u32;
+        return;
+    }
+
+    // Split at the median face along the longest axis of the face centroids. Partitioning
+    // around the median instead of sorting keeps the build at O(n log n).
+    let centroid_bounds = faces.iter().fold(Aabb::default(), |acc, face| {
+        let c = face_bbox(positions, face).centroid();
+        Aabb::surrounding(&acc, &Aabb::new(c, c))
+    });
+    let axis = centroid_bounds.longest_axis();
+    let mid = faces.len() / 2;
+    faces.select_nth_unstable_by(mid, |a, b| {
+        let ca = face_bbox(positions, a).centroid()[axis];
+        let cb = face_bbox(positions, b).centroid()[axis];
+        ca.total_cmp(&cb)
+    });
+
+    let (left, right) = faces.split_at_mut(mid);
+    build_nodes(nodes, positions, left, start);
+    nodes[index].right = nodes.len() as u32;
+    build_nodes(nodes, positions, right, start + mid);
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        // Closest hit as (face, t, barycentric u, barycentric v).
+        let mut closest: Option<(usize, f32, f32, f32)> = None;
+        let mut closest_so_far = ray_t.end;
+        // The tree is balanced, so its depth, and the stack, stay small.
+        let mut stack = [0usize; 64];
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+            let node_t = Range {
+                start: ray_t.start,
+                end: closest_so_far,
+            };
+            if !node.bbox.hit(r, node_t.clone()) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack[stack_len] = node.right as usize;
+                stack[stack_len + 1] = index + 1;
+                stack_len += 2;
+                continue;
+            }
+
+            let first = node.start as usize;
+            for (i, face) in self.faces[first..first + node.count as usize]
+                .iter()
+                .enumerate()
+            {
+                let (a, b, c) = self.corners(face);
+                let face_t = Range {
+                    start: ray_t.start,
+                    end: closest_so_far,
+                };
+                if let Some((t, u, v)) = intersect_triangle(r, &face_t, a, b, c) {
+                    closest_so_far = t;
+                    closest = Some((first + i, t, u, v));
+                }
+            }
+        }
+
+        let (index, t, u, v) = closest?;
+        let face = &self.faces[index];
+        let (a, b, c) = self.corners(face);
+        let w = 1.0 - u - v;
+
+        // The geometric normal decides which side was hit. The interpolated vertex normal, if
+        // any, only shades, turned to the same side as the geometric one.
+        let geometric_normal = Vec3::unit_vector(Vec3::cross(&(b - a), &(c - a)));
+        let (front_face, normal) = face_normal(r, geometric_normal);
+        let normal = match face.normals {
+            Some([na, nb, nc]) => {
+                let shading_normal = Vec3::unit_vector(
+                    w * self.normals[na as usize]
+                        + u * self.normals[nb as usize]
+                        + v * self.normals[nc as usize],
+                );
+                if Vec3::dot(&shading_normal, &normal) < 0.0 {
+                    -shading_normal
+                } else {
+                    shading_normal
+                }
+            }
+            None => normal,
+        };
+
+        let (tex_u, tex_v) = match face.uvs {
+            Some([ta, tb, tc]) => {
+                let (ua, va) = self.uvs[ta as usize];
+                let (ub, vb) = self.uvs[tb as usize];
+                let (uc, vc) = self.uvs[tc as usize];
+                (w * ua + u * ub + v * uc, w * va + u * vb + v * vc)
+            }
+            None => (u, v),
+        };
+
+        let color = if self.colors.is_empty() {
+            None
+        } else {
+            let [ca, cb, cc] = face.vertices.map(|i| self.colors[i as usize]);
+            Some(w * ca + u * cb + v * cc)
+        };
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            material: &self.materials[face.material as usize],
+            t,
+            u: tex_u,
+            v: tex_v,
+            front_face,
+            color,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::default(), |root| root.bbox)
+    }
+}
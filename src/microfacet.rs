@@ -0,0 +1,214 @@
+use std::f32::consts::PI;
+
+use crate::rng::random;
+use crate::vec3::Vec3;
+
+// Rough surfaces modeled as tiny mirror facets with normals following the GGX distribution,
+// and Smith's masking-shadowing for the facets hidden from the incoming or outgoing direction.
+// Directions are in the surface's frame with the normal along z, `wo` towards where the ray
+// came from and `wi` the scattered direction. The roughness alpha is the square of the
+// perceptual roughness the materials take.
+
+pub fn alpha(roughness: f32) -> f32 {
+    // Near-zero roughness is a mirror, kept finite.
+    roughness.clamp(0.01, 1.0).powi(2)
+}
+
+fn ggx_d(h: Vec3, alpha: f32) -> f32 {
+    // Density of the facet normals over the projected area.
+    let a2 = alpha * alpha;
+    let d = h.z() * h.z() * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+fn lambda(w: Vec3, alpha: f32) -> f32 {
+    let cos2 = w.z() * w.z();
+    let tan2 = (1.0 - cos2).max(0.0) / cos2;
+    0.5 * ((1.0 + alpha * alpha * tan2).sqrt() - 1.0)
+}
+
+fn smith_g1(w: Vec3, alpha: f32) -> f32 {
+    // Fraction of the facets visible from w.
+    1.0 / (1.0 + lambda(w, alpha))
+}
+
+fn smith_g2(wo: Vec3, wi: Vec3, alpha: f32) -> f32 {
+    // Fraction visible from both directions, with the height-correlated form.
+    1.0 / (1.0 + lambda(wo, alpha) + lambda(wi, alpha))
+}
+
+fn sample_visible_normal(wo: Vec3, alpha: f32) -> Vec3 {
+    // Heitz's sampling of the facet normals in proportion to their area seen from wo: stretch
+    // the surface to make the facets a hemisphere, pick a point on its disk as seen from wo,
+    // and unstretch the normal there.
+    let vh = Vec3::unit_vector(Vec3::new(alpha * wo.x(), alpha * wo.y(), wo.z()));
+    let len2 = vh.x() * vh.x() + vh.y() * vh.y();
+    let t1 = if len2 > 0.0 {
+        Vec3::new(-vh.y(), vh.x(), 0.0) / len2.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = Vec3::cross(&vh, &t1);
+
+    let r = random::<f32>().sqrt();
+    let phi = 2.0 * PI * random::<f32>();
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.z());
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+    let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+    Vec3::unit_vector(Vec3::new(alpha * nh.x(), alpha * nh.y(), nh.z().max(1e-6)))
+}
+
+fn visible_normal_pdf(wo: Vec3, h: Vec3, alpha: f32) -> f32 {
+    smith_g1(wo, alpha) * Vec3::dot(&wo, &h).max(0.0) * ggx_d(h, alpha) / wo.z()
+}
+
+fn reflect(wo: Vec3, h: Vec3) -> Vec3 {
+    2.0 * Vec3::dot(&wo, &h) * h - wo
+}
+
+fn fresnel_conductor(cos_theta: f32, eta: Vec3, k: Vec3) -> Vec3 {
+    // Unpolarized reflectance of a metal with complex refractive index eta + ik, per channel.
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1.0 - cos2;
+    let channel = |eta: f32, k: f32| {
+        let t0 = eta * eta - k * k - sin2;
+        let a2b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2b2 + t0)).max(0.0).sqrt();
+        let t1 = a2b2 + cos2;
+        let t2 = 2.0 * cos_theta * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rs + rp)
+    };
+    Vec3::new(
+        channel(eta.x(), k.x()),
+        channel(eta.y(), k.y()),
+        channel(eta.z(), k.z()),
+    )
+}
+
+fn fresnel_dielectric(cos_theta: f32, eta: f32) -> f32 {
+    // Unpolarized reflectance into a medium eta times as dense, 1 past the critical angle.
+    let sin2_t = (1.0 - cos_theta * cos_theta) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_theta - cos_t) / (eta * cos_theta + cos_t);
+    let perpendicular = (cos_theta - eta * cos_t) / (cos_theta + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+// Complex refractive indices of common metals at red, green and blue wavelengths.
+#[derive(Debug, Clone, Copy)]
+pub enum Metal {
+    Gold,
+    Copper,
+    Aluminium,
+}
+
+impl Metal {
+    pub fn ior(self) -> (Vec3, Vec3) {
+        // The real part eta and the extinction k.
+        match self {
+            Metal::Gold => (
+                Vec3::new(0.143, 0.374, 1.442),
+                Vec3::new(3.983, 2.385, 1.603),
+            ),
+            Metal::Copper => (
+                Vec3::new(0.200, 0.924, 1.102),
+                Vec3::new(3.912, 2.452, 2.142),
+            ),
+            Metal::Aluminium => (
+                Vec3::new(1.657, 0.880, 0.521),
+                Vec3::new(9.224, 6.270, 4.837),
+            ),
+        }
+    }
+}
+
+pub fn conductor_sample(wo: Vec3, eta: Vec3, k: Vec3, alpha: f32) -> Option<(Vec3, Vec3)> {
+    // A scattered direction and its weight, the reflected light over the density of picking it.
+    let h = sample_visible_normal(wo, alpha);
+    let wi = reflect(wo, h);
+    if wi.z() <= 0.0 {
+        return None;
+    }
+    let weight = smith_g2(wo, wi, alpha) / smith_g1(wo, alpha);
+    Some((wi, weight * fresnel_conductor(Vec3::dot(&wo, &h), eta, k)))
+}
+
+pub fn conductor_eval(wo: Vec3, wi: Vec3, eta: Vec3, k: Vec3, alpha: f32) -> (Vec3, f32) {
+    // The BRDF times the cosine of wi, and the density of `conductor_sample` picking wi.
+    if wo.z() <= 0.0 || wi.z() <= 0.0 {
+        return (Vec3::default(), 0.0);
+    }
+    let h = Vec3::unit_vector(wo + wi);
+    let cos_h = Vec3::dot(&wo, &h);
+    let d = ggx_d(h, alpha);
+    let fresnel = fresnel_conductor(cos_h, eta, k);
+    let value = (d * smith_g2(wo, wi, alpha) / (4.0 * wo.z())) * fresnel;
+    let pdf = visible_normal_pdf(wo, h, alpha) / (4.0 * cos_h);
+    (value, pdf)
+}
+
+pub fn dielectric_sample(wo: Vec3, eta: f32, alpha: f32) -> Option<(Vec3, f32)> {
+    // A reflected or refracted direction, picked by the facet's Fresnel reflectance, and its
+    // weight. `eta` is the refractive index of the other side relative to wo's.
+    let h = sample_visible_normal(wo, alpha);
+    let cos_h = Vec3::dot(&wo, &h);
+    let wi = if random::<f32>() < fresnel_dielectric(cos_h, eta) {
+        let wi = reflect(wo, h);
+        if wi.z() <= 0.0 {
+            return None;
+        }
+        wi
+    } else {
+        let cos_t = (1.0 - (1.0 - cos_h * cos_h) / (eta * eta)).sqrt();
+        let wi = -wo / eta + (cos_h / eta - cos_t) * h;
+        if wi.z() >= 0.0 {
+            return None;
+        }
+        wi
+    };
+    Some((wi, smith_g2(wo, wi, alpha) / smith_g1(wo, alpha)))
+}
+
+pub fn dielectric_eval(wo: Vec3, wi: Vec3, eta: f32, alpha: f32) -> (f32, f32) {
+    // The BSDF times the cosine of wi, and the density of `dielectric_sample` picking wi. Like
+    // the smooth dielectric, refraction doesn't scale the radiance by the change of index.
+    let none = (0.0, 0.0);
+    let reflected = wi.z() > 0.0;
+    if wo.z() <= 0.0 || wi.z() == 0.0 {
+        return none;
+    }
+
+    // The facet normal that turns wo into wi, facing wo's side.
+    let h = if reflected { wo + wi } else { wo + eta * wi };
+    if h.near_zero() {
+        return none;
+    }
+    let h = Vec3::unit_vector(h);
+    let h = if h.z() < 0.0 { -h } else { h };
+    let (cos_o, cos_i) = (Vec3::dot(&wo, &h), Vec3::dot(&wi, &h));
+    if cos_o <= 0.0 || (cos_i > 0.0) != reflected {
+        return none;
+    }
+
+    let d = ggx_d(h, alpha);
+    let g = smith_g2(wo, wi, alpha);
+    let fresnel = fresnel_dielectric(cos_o, eta);
+    let pdf = visible_normal_pdf(wo, h, alpha);
+    if reflected {
+        let value = d * g * fresnel / (4.0 * wo.z());
+        (value, pdf * fresnel / (4.0 * cos_o))
+    } else {
+        let denom = (cos_i + cos_o / eta).powi(2);
+        let value = d * g * (1.0 - fresnel) * cos_i.abs() * cos_o / (denom * wo.z());
+        (value, pdf * (1.0 - fresnel) * cos_i.abs() / denom)
+    }
+}
@@ -0,0 +1,63 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::sphere::hit_sphere;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// A sphere moving in a straight line from center0 at time0 to center1 at time1.
+#[derive(Debug, Clone)]
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Vec3,
+        center1: Vec3,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: Material,
+    ) -> MovingSphere {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self, time: f32) -> Vec3 {
+        // Rays from outside [time0, time1] see the sphere at the nearest end of its path.
+        let s = if self.time1 > self.time0 {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.center0 + s * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center(r.time()), self.radius, &self.material, r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // The box around both ends covers the whole path.
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        let box0 = Aabb::new(self.center0 - rvec, self.center0 + rvec);
+        let box1 = Aabb::new(self.center1 - rvec, self.center1 + rvec);
+        Aabb::surrounding(&box0, &box1)
+    }
+}
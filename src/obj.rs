@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::mesh::{MeshError, MeshFace, TriangleMesh};
+use crate::texture::{ImageTexture, SolidColor, Texture};
+use crate::vec3::Vec3;
+
+// Wavefront OBJ loader. Polygons are split into triangle fans. Materials come from the MTL
+// libraries named by `mtllib`, looked up relative to the OBJ file, unless `material` overrides
+// them for the whole mesh.
+pub fn load_obj(path: &Path, material: Option<Material>) -> Result<TriangleMesh, MeshError> {
+    let src = fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut faces = Vec::new();
+
+    let mut mtl: HashMap<String, MtlDesc> = HashMap::new();
+    // Faces before the first `usemtl` get the default MTL material.
+    let default_material = match &material {
+        Some(material) => material.clone(),
+        None => MtlDesc::default().build(dir)?,
+    };
+    let mut materials = vec![default_material];
+    let mut material_indices: HashMap<String, u32> = HashMap::new();
+    let mut current_material = 0;
+
+    for (n, line) in src.lines().enumerate() {
+        let error = |message: String| MeshError::Parse(format!("line {}: {}", n + 1, message));
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parse_vec3(&mut tokens).map_err(error)?),
+            Some("vn") => normals.push(parse_vec3(&mut tokens).map_err(error)?),
+            Some("vt") => {
+                let u = parse_f32(tokens.next()).map_err(error)?;
+                let v = tokens
+                    .next()
+                    .map_or(Ok(0.0), |v| parse_f32(Some(v)))
+                    .map_err(error)?;
+                uvs.push((u, v));
+            }
+            Some("f") => {
+                let counts = (positions.len(), uvs.len(), normals.len());
+                let corners = tokens
+                    .map(|corner| parse_corner(corner, counts))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if corners.len() < 3 {
+                    return Err(error("face with less than 3 vertices".to_string()));
+                }
+                for i in 1..corners.len() - 1 {
+                    faces.push(triangle(
+                        [corners[0], corners[i], corners[i + 1]],
+                        current_material,
+                    ));
+                }
+            }
+            Some("mtllib") if material.is_none() => {
+                for name in tokens {
+                    let lib_path = dir.join(name);
+                    let lib_src = fs::read_to_string(&lib_path)?;
+                    parse_mtl(&lib_src, &mut mtl).map_err(|message| {
+                        MeshError::Parse(format!("{}: {}", lib_path.display(), message))
+                    })?;
+                }
+            }
+            Some("usemtl") if material.is_none() => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                current_material = match material_indices.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let desc = mtl
+                            .get(&name)
+                            .ok_or_else(|| error(format!("unknown material `{}`", name)))?;
+                        let index = materials.len() as u32;
+                        materials.push(desc.build(dir)?);
+                        material_indices.insert(name, index);
+                        index
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TriangleMesh::new(positions, normals, uvs, faces, materials))
+}
+
+// Position, texture coordinate and normal indices of a face corner.
+type Corner = (u32, Option<u32>, Option<u32>);
+
+fn triangle(corners: [Corner; 3], material: u32) -> MeshFace {
+    let uvs = corners.map(|c| c.1);
+    let normals = corners.map(|c| c.2);
+    MeshFace {
+        vertices: corners.map(|c| c.0),
+        uvs: match uvs {
+            [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+            _ => None,
+        },
+        normals: match normals {
+            [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+            _ => None,
+        },
+        material,
+    }
+}
+
+fn parse_corner(corner: &str, counts: (usize, usize, usize)) -> Result<Corner, String> {
+    // One of v, v/vt, v//vn or v/vt/vn.
+    let mut parts = corner.split('/');
+    let v = parse_index(parts.next(), counts.0)?
+        .ok_or_else(|| format!("missing vertex index in `{}`", corner))?;
+    let vt = parse_index(parts.next(), counts.1)?;
+    let vn = parse_index(parts.next(), counts.2)?;
+    Ok((v, vt, vn))
+}
+
+fn parse_index(token: Option<&str>, count: usize) -> Result<Option<u32>, String> {
+    // OBJ indices start at 1, negative ones count back from the last element defined so far.
+    let token = match token {
+        Some(token) if !token.is_empty() => token,
+        _ => return Ok(None),
+    };
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("invalid index `{}`", token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", index));
+    }
+    Ok(Some(resolved as u32))
+}
+
+fn parse_f32(token: Option<&str>) -> Result<f32, String> {
+    let token = token.ok_or_else(|| "missing number".to_string())?;
+    token
+        .parse()
+        .map_err(|_| format!("invalid number `{}`", token))
+}
+
+fn parse_vec3(tokens: &mut SplitWhitespace) -> Result<Vec3, String> {
+    Ok(Vec3::new(
+        parse_f32(tokens.next())?,
+        parse_f32(tokens.next())?,
+        parse_f32(tokens.next())?,
+    ))
+}
+
+// The subset of an MTL material the renderer can map onto its own materials.
+#[derive(Debug, Clone)]
+struct MtlDesc {
+    kd: Vec3,                // Diffuse color
+    ks: Vec3,                // Specular color
+    ke: Vec3,                // Emitted radiance
+    ns: f32,                 // Specular exponent
+    ni: f32,                 // Index of refraction
+    dissolve: f32,           // Opacity, 1 - transparency
+    illum: i32,              // Illumination model
+    map_kd: Option<PathBuf>, // Diffuse color texture
+}
+
+impl Default for MtlDesc {
+    fn default() -> Self {
+        MtlDesc {
+            kd: Vec3::new(0.8, 0.8, 0.8),
+            ks: Vec3::default(),
+            ke: Vec3::default(),
+            ns: 0.0,
+            ni: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+            map_kd: None,
+        }
+    }
+}
+
+impl MtlDesc {
+    fn build(&self, dir: &Path) -> Result<Material, MeshError> {
+        let max = |c: Vec3| c.x().max(c.y()).max(c.z());
+
+        // Emitters first, then transparent materials and refraction illumination models as
+        // glass, then materials with a specular color stronger than their diffuse one as metal.
+        if max(self.ke) > 0.0 {
+            return Ok(Material::DiffuseLight {
+                emit: self.ke,
+                two_sided: false,
+            });
+        }
+        if self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            let ir = if self.ni > 1.0 { self.ni } else { 1.5 };
+            return Ok(Material::Dielectric { ir });
+        }
+        if max(self.ks) > max(self.kd) {
+            // Map the Phong exponent to a fuzz radius, sharper highlights giving less fuzz.
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt().min(1.0);
+            return Ok(Material::Metal {
+                albedo: Arc::new(SolidColor::new(self.ks)),
+                fuzz,
+            });
+        }
+
+        let albedo: Arc<dyn Texture> = match &self.map_kd {
+            Some(map) => {
+                let path = dir.join(map);
+                match ImageTexture::load(&path) {
+                    Ok(texture) => Arc::new(texture),
+                    Err(err) => return Err(MeshError::Image(path, err)),
+                }
+            }
+            None => Arc::new(SolidColor::new(self.kd)),
+        };
+        Ok(Material::Lambertian { albedo })
+    }
+}
+
+fn parse_mtl(src: &str, materials: &mut HashMap<String, MtlDesc>) -> Result<(), String> {
+    let mut current: Option<(String, MtlDesc)> = None;
+
+    for (n, line) in src.lines().enumerate() {
+        let error = |message: String| format!("line {}: {}", n + 1, message);
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            if let Some((name, desc)) = current.take() {
+                materials.insert(name, desc);
+            }
+            let name = tokens.collect::<Vec<_>>().join(" ");
+            current = Some((name, MtlDesc::default()));
+            continue;
+        }
+
+        let Some((_, desc)) = current.as_mut() else {
+            continue;
+        };
+        match keyword {
+            "Kd" => desc.kd = parse_vec3(&mut tokens).map_err(error)?,
+            "Ks" => desc.ks = parse_vec3(&mut tokens).map_err(error)?,
+            "Ke" => desc.ke = parse_vec3(&mut tokens).map_err(error)?,
+            "Ns" => desc.ns = parse_f32(tokens.next()).map_err(error)?,
+            "Ni" => desc.ni = parse_f32(tokens.next()).map_err(error)?,
+            "d" => desc.dissolve = parse_f32(tokens.next()).map_err(error)?,
+            "Tr" => desc.dissolve = 1.0 - parse_f32(tokens.next()).map_err(error)?,
+            "illum" => {
+                desc.illum = parse_f32(tokens.next()).map_err(error)? as i32;
+            }
+            // Texture options come before the file name, so take the last token.
+            "map_Kd" => desc.map_kd = tokens.last().map(Into::into),
+            _ => {}
+        }
+    }
+
+    if let Some((name, desc)) = current {
+        materials.insert(name, desc);
+    }
+    Ok(())
+}
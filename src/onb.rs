@@ -0,0 +1,38 @@
+use crate::vec3::Vec3;
+
+// Orthonormal basis with w along a given direction, used to map samples generated around the
+// z axis onto an arbitrary direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Onb {
+        let w = Vec3::unit_vector(n);
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::unit_vector(Vec3::cross(&w, &a));
+        let u = Vec3::cross(&w, &v);
+        Onb { u, v, w }
+    }
+
+    pub fn transform(&self, a: Vec3) -> Vec3 {
+        // Transform from basis coordinates to local space.
+        (a.x() * self.u) + (a.y() * self.v) + (a.z() * self.w)
+    }
+
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        // Transform from local space to basis coordinates, the inverse of `transform`.
+        Vec3::new(
+            Vec3::dot(&a, &self.u),
+            Vec3::dot(&a, &self.v),
+            Vec3::dot(&a, &self.w),
+        )
+    }
+}
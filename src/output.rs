@@ -0,0 +1,190 @@
+use exr::prelude::{f16, Image, IntoSample, SpecificChannels, Vec2, WritableImage};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::framebuffer::Framebuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,      // 8-bit RGB PNG
+    Ppm,      // Binary (P6) PPM
+    PpmAscii, // ASCII (P3) PPM
+    Exr,      // OpenEXR with 32-bit float channels
+    ExrHalf,  // OpenEXR with 16-bit half float channels
+    Hdr,      // Radiance RGBE
+    Pfm,      // Portable Float Map
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        // Guess the format from the file extension. ASCII PPM and half float EXR share their
+        // extensions with binary PPM and float EXR, so they have to be asked for explicitly.
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "exr" => Some(ImageFormat::Exr),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ImageFormat, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "ppm" => Ok(ImageFormat::Ppm),
+            "ppm-ascii" => Ok(ImageFormat::PpmAscii),
+            "exr" => Ok(ImageFormat::Exr),
+            "exr-half" => Ok(ImageFormat::ExrHalf),
+            "hdr" => Ok(ImageFormat::Hdr),
+            "pfm" => Ok(ImageFormat::Pfm),
+            _ => Err(format!("unknown image format `{}`", s)),
+        }
+    }
+}
+
+fn clamp(rng: &Range<f32>, val: f32) -> f32 {
+    if val < rng.start {
+        rng.start
+    } else if val > rng.end {
+        rng.end
+    } else {
+        val
+    }
+}
+
+fn linear_to_gama(linear_component: f32) -> f32 {
+    linear_component.sqrt()
+}
+
+fn to_rgb8(image: &Framebuffer) -> Vec<u8> {
+    // Apply the linear to gamma transform and translate each color component to [0, 255].
+    let intensity: Range<f32> = Range {
+        start: 0.000,
+        end: 0.999,
+    };
+    image
+        .pixels()
+        .iter()
+        .flat_map(|pixel| [pixel.r(), pixel.g(), pixel.b()])
+        .map(|c| (255.99 * clamp(&intensity, linear_to_gama(c))) as u8)
+        .collect()
+}
+
+pub fn write_image(image: &Framebuffer, path: &Path, format: ImageFormat) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ImageFormat::Png => write_png(image, &mut out)?,
+        ImageFormat::Ppm => write_ppm(image, &mut out)?,
+        ImageFormat::PpmAscii => write_ppm_ascii(image, &mut out)?,
+        ImageFormat::Exr => write_exr(image, &mut out, |c| c)?,
+        ImageFormat::ExrHalf => write_exr(image, &mut out, f16::from_f32)?,
+        ImageFormat::Hdr => write_hdr(image, &mut out)?,
+        ImageFormat::Pfm => write_pfm(image, &mut out)?,
+    }
+    out.flush()
+}
+
+fn write_png(image: &Framebuffer, out: &mut impl Write) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&to_rgb8(image))
+        .map_err(io::Error::other)
+}
+
+fn write_ppm(image: &Framebuffer, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n{}\n", image.width, image.height, 255)?;
+    out.write_all(&to_rgb8(image))
+}
+
+fn write_ppm_ascii(image: &Framebuffer, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "P3\n{} {}\n{}", image.width, image.height, 255)?;
+    for rgb in to_rgb8(image).chunks(3) {
+        writeln!(out, "{} {} {}", rgb[0], rgb[1], rgb[2])?;
+    }
+    Ok(())
+}
+
+// The HDR writers below store the unclamped linear radiance, without any gamma transform.
+
+fn write_exr<T: IntoSample>(
+    image: &Framebuffer,
+    out: &mut (impl Write + Seek),
+    sample: fn(f32) -> T,
+) -> io::Result<()> {
+    let channels = SpecificChannels::rgb(|Vec2(x, y)| {
+        let pixel = image.pixel(x, y);
+        (sample(pixel.r()), sample(pixel.g()), sample(pixel.b()))
+    });
+    Image::from_channels((image.width, image.height), channels)
+        .write()
+        .to_buffered(out)
+        .map_err(io::Error::other)
+}
+
+fn to_rgbe(r: f32, g: f32, b: f32) -> [u8; 4] {
+    // Share one exponent between the three components, taken from the largest of them, and
+    // store each component as an 8-bit mantissa relative to it. Black, NaN and negative
+    // pixels are stored as zero, and ones too bright for the largest exponent saturate.
+    let v = r.max(g).max(b);
+    if v.is_nan() || v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let exponent = v.log2().floor().min(126.0) as i32 + 1;
+    let scale = 256.0 / 2f32.powi(exponent);
+    [
+        (r.max(0.0) * scale) as u8,
+        (g.max(0.0) * scale) as u8,
+        (b.max(0.0) * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+fn write_hdr(image: &Framebuffer, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n")?;
+    writeln!(out, "-Y {} +X {}", image.height, image.width)?;
+    for pixel in image.pixels() {
+        out.write_all(&to_rgbe(pixel.r(), pixel.g(), pixel.b()))?;
+    }
+    Ok(())
+}
+
+fn write_pfm(image: &Framebuffer, out: &mut impl Write) -> io::Result<()> {
+    // A negative scale marks little-endian data. Scanlines are stored bottom to top.
+    write!(out, "PF\n{} {}\n-1.0\n", image.width, image.height)?;
+    for y in (0..image.height).rev() {
+        for x in 0..image.width {
+            let pixel = image.pixel(x, y);
+            for c in [pixel.r(), pixel.g(), pixel.b()] {
+                out.write_all(&c.to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgbe_out_of_range() {
+        assert_eq!(to_rgbe(0.0, 0.0, 0.0), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(-1.0, -2.0, 0.0), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(f32::NAN, f32::NAN, f32::NAN), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(1.0, 0.5, 0.0), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(f32::MAX, 0.0, 0.0), [255, 0, 0, 255]);
+        assert_eq!(to_rgbe(f32::INFINITY, 1.0, 0.0), [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,431 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::mesh::{MeshError, MeshFace, TriangleMesh};
+use crate::texture::SolidColor;
+use crate::vec3::Vec3;
+
+// Upper bound on the capacity reserved up front from element counts in the header, so a
+// corrupt count can't make the loader allocate memory the file doesn't back.
+const MAX_RESERVE: usize = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn color_scale(self) -> f64 {
+        // Integer colors use the full range of their type, float colors are already in [0, 1].
+        match self {
+            Scalar::U8 => 255.0,
+            Scalar::U16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar), // Name, count type and item type
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, names: &[&str]) -> Option<(usize, Scalar)> {
+        // Position of the first scalar property with one of `names` among the element's
+        // scalars, with its type.
+        self.properties
+            .iter()
+            .filter_map(|p| match p {
+                Property::Scalar(name, ty) => Some((name, *ty)),
+                Property::List(..) => None,
+            })
+            .enumerate()
+            .find(|(_, (name, _))| names.contains(&name.as_str()))
+            .map(|(i, (_, ty))| (i, ty))
+    }
+}
+
+// Stanford PLY loader for ascii and binary files. Faces are split into triangle fans. Vertex
+// normals, texture coordinates and colors are used when present, and the file is streamed so
+// memory stays proportional to the mesh itself.
+pub fn load_ply(path: &Path, material: Option<Material>) -> Result<TriangleMesh, MeshError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let (format, elements, header_lines) = read_header(&mut reader)?;
+    let mut body = Body {
+        reader,
+        format,
+        line: String::new(),
+        line_number: header_lines,
+        values: Vec::new(),
+        next: 0,
+    };
+
+    let vertex_count = elements
+        .iter()
+        .find(|e| e.name == "vertex")
+        .map_or(0, |e| e.count);
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut faces = Vec::new();
+
+    let mut scalars = Vec::new();
+    let mut indices = Vec::new();
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let position = [
+                    element.scalar(&["x"]),
+                    element.scalar(&["y"]),
+                    element.scalar(&["z"]),
+                ];
+                let [Some(x), Some(y), Some(z)] = position else {
+                    return Err(MeshError::Parse("vertex without x, y and z".to_string()));
+                };
+                let normal = [
+                    element.scalar(&["nx"]),
+                    element.scalar(&["ny"]),
+                    element.scalar(&["nz"]),
+                ];
+                let uv = [
+                    element.scalar(&["u", "s", "texture_u", "texture_s"]),
+                    element.scalar(&["v", "t", "texture_v", "texture_t"]),
+                ];
+                let color = [
+                    element.scalar(&["red", "diffuse_red"]),
+                    element.scalar(&["green", "diffuse_green"]),
+                    element.scalar(&["blue", "diffuse_blue"]),
+                ];
+
+                let reserve = element.count.min(MAX_RESERVE);
+                positions.reserve(reserve);
+                if normal.iter().all(Option::is_some) {
+                    normals.reserve(reserve);
+                }
+                if uv.iter().all(Option::is_some) {
+                    uvs.reserve(reserve);
+                }
+                if color.iter().all(Option::is_some) {
+                    colors.reserve(reserve);
+                }
+
+                for _ in 0..element.count {
+                    body.read_element(element, &mut scalars, &mut indices, None)?;
+                    let get = |(i, _): (usize, Scalar)| scalars[i] as f32;
+                    positions.push(Vec3::new(get(x), get(y), get(z)));
+                    if let [Some(nx), Some(ny), Some(nz)] = normal {
+                        normals.push(Vec3::new(get(nx), get(ny), get(nz)));
+                    }
+                    if let [Some(u), Some(v)] = uv {
+                        uvs.push((get(u), get(v)));
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color {
+                        // Stored colors are gamma encoded, like image textures.
+                        let channel = |(i, ty): (usize, Scalar)| {
+                            let c = (scalars[i] / ty.color_scale()) as f32;
+                            c * c
+                        };
+                        colors.push(Vec3::new(channel(r), channel(g), channel(b)));
+                    }
+                }
+            }
+            "face" => {
+                let list = ["vertex_indices", "vertex_index"];
+                if !element
+                    .properties
+                    .iter()
+                    .any(|p| matches!(p, Property::List(name, ..) if list.contains(&name.as_str())))
+                {
+                    return Err(MeshError::Parse("face without vertex_indices".to_string()));
+                }
+
+                faces.reserve(element.count.min(MAX_RESERVE));
+                for n in 0..element.count {
+                    body.read_element(element, &mut scalars, &mut indices, Some(&list))?;
+                    if indices.len() < 3 {
+                        return Err(MeshError::Parse(format!(
+                            "face {}: less than 3 vertices",
+                            n
+                        )));
+                    }
+                    if let Some(index) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+                        return Err(MeshError::Parse(format!(
+                            "face {}: vertex index {} out of range",
+                            n, index
+                        )));
+                    }
+                    for i in 1..indices.len() - 1 {
+                        let vertices = [indices[0], indices[i], indices[i + 1]];
+                        faces.push(MeshFace {
+                            vertices,
+                            normals: (!normals.is_empty()).then_some(vertices),
+                            uvs: (!uvs.is_empty()).then_some(vertices),
+                            material: 0,
+                        });
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_element(element, &mut scalars, &mut indices, None)?;
+                }
+            }
+        }
+    }
+
+    // Without a material, meshes render with the default MTL gray, or their vertex colors.
+    let material = material.unwrap_or_else(|| Material::Lambertian {
+        albedo: Arc::new(SolidColor::new(Vec3::new(0.8, 0.8, 0.8))),
+    });
+    let mesh = TriangleMesh::new(positions, normals, uvs, faces, vec![material]);
+    if colors.is_empty() {
+        Ok(mesh)
+    } else {
+        Ok(mesh.with_colors(colors))
+    }
+}
+
+fn read_header(reader: &mut impl BufRead) -> Result<(Format, Vec<Element>, usize), MeshError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(MeshError::Parse("missing end_header".to_string()));
+        }
+        line_number += 1;
+        let error =
+            |message: String| MeshError::Parse(format!("line {}: {}", line_number, message));
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if tokens != ["ply"] {
+                return Err(error("not a PLY file".to_string()));
+            }
+            continue;
+        }
+        match tokens.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(error(format!("unknown format `{}`", name))),
+                });
+            }
+            ["element", name, count] => {
+                let count = count
+                    .parse()
+                    .map_err(|_| error(format!("invalid element count `{}`", count)))?;
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", count_type, item_type, name] => {
+                let count_type = parse_scalar(count_type).map_err(error)?;
+                let item_type = parse_scalar(item_type).map_err(error)?;
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before element".to_string()))?;
+                element
+                    .properties
+                    .push(Property::List(name.to_string(), count_type, item_type));
+            }
+            ["property", ty, name] => {
+                let ty = parse_scalar(ty).map_err(error)?;
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before element".to_string()))?;
+                element
+                    .properties
+                    .push(Property::Scalar(name.to_string(), ty));
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(error(format!("invalid header line `{}`", line.trim()))),
+        }
+    }
+
+    let format = format.ok_or_else(|| MeshError::Parse("missing format".to_string()))?;
+    Ok((format, elements, line_number))
+}
+
+fn parse_scalar(name: &str) -> Result<Scalar, String> {
+    Scalar::parse(name).ok_or_else(|| format!("unknown property type `{}`", name))
+}
+
+// The data following the header. Values of every type are read as f64, which holds all of
+// them exactly.
+struct Body<R> {
+    reader: R,
+    format: Format,
+    line: String,       // Current ascii line
+    line_number: usize, // Of the current ascii line
+    values: Vec<f64>,   // Values of the current ascii line
+    next: usize,        // Next unread value of the current ascii line
+}
+
+impl<R: BufRead> Body<R> {
+    fn read_element(
+        &mut self,
+        element: &Element,
+        scalars: &mut Vec<f64>,
+        indices: &mut Vec<u32>,
+        index_list: Option<&[&str]>,
+    ) -> Result<(), MeshError> {
+        // Reads one instance of `element`, collecting its scalar properties into `scalars` and
+        // the items of the list named in `index_list` into `indices`. Other lists are skipped.
+        if self.format == Format::Ascii {
+            self.next_line()?;
+        }
+        scalars.clear();
+        indices.clear();
+        for property in &element.properties {
+            match property {
+                Property::Scalar(_, ty) => scalars.push(self.read(*ty)?),
+                Property::List(name, count_type, item_type) => {
+                    let count = self.read(*count_type)?;
+                    if count < 0.0 {
+                        return Err(self.error(format!("negative list length {}", count)));
+                    }
+                    let keep = index_list.is_some_and(|list| list.contains(&name.as_str()));
+                    for _ in 0..count as usize {
+                        let item = self.read(*item_type)?;
+                        if keep {
+                            if item < 0.0 || item > u32::MAX as f64 {
+                                return Err(self.error(format!("invalid index {}", item)));
+                            }
+                            indices.push(item as u32);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next_line(&mut self) -> Result<(), MeshError> {
+        // Skips blank lines, so the number of values in a line is only checked as they're read.
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Err(MeshError::Parse("unexpected end of file".to_string()));
+            }
+            self.line_number += 1;
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+
+        self.values.clear();
+        self.next = 0;
+        for token in self.line.split_whitespace() {
+            match token.parse() {
+                Ok(value) => self.values.push(value),
+                Err(_) => {
+                    let message = format!("invalid number `{}`", token);
+                    return Err(self.error(message));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, ty: Scalar) -> Result<f64, MeshError> {
+        if self.format == Format::Ascii {
+            let value = self
+                .values
+                .get(self.next)
+                .copied()
+                .ok_or_else(|| self.error("missing value".to_string()))?;
+            self.next += 1;
+            return Ok(value);
+        }
+
+        let mut bytes = [0u8; 8];
+        let bytes = &mut bytes[..ty.size()];
+        if let Err(err) = self.reader.read_exact(bytes) {
+            return Err(match err.kind() {
+                io::ErrorKind::UnexpectedEof => {
+                    MeshError::Parse("unexpected end of file".to_string())
+                }
+                _ => MeshError::Io(err),
+            });
+        }
+        if self.format == Format::BinaryBigEndian {
+            bytes.reverse();
+        }
+        Ok(match ty {
+            Scalar::I8 => i8::from_le_bytes([bytes[0]]) as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        })
+    }
+
+    fn error(&self, message: String) -> MeshError {
+        match self.format {
+            Format::Ascii => MeshError::Parse(format!("line {}: {}", self.line_number, message)),
+            _ => MeshError::Parse(message),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::rng::random;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Parallelogram with corner q and edges u and v.
+#[derive(Debug, Clone)]
+pub struct Quad {
+    pub q: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: Material,
+    w: Vec3,      // Maps a point on the plane to its (alpha, beta) coordinates
+    normal: Vec3, // Unit normal of the plane, along u x v
+    d: f32,       // Plane constant, normal . p = d
+    area: f32,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, material: Material) -> Quad {
+        let n = Vec3::cross(&u, &v);
+        let normal = Vec3::unit_vector(n);
+        let bbox = Aabb::surrounding(&Aabb::new(q, q + u + v), &Aabb::new(q + u, q + v)).pad();
+
+        Quad {
+            q,
+            u,
+            v,
+            material,
+            w: n / Vec3::dot(&n, &n),
+            normal,
+            d: Vec3::dot(&normal, &q),
+            area: n.length(),
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // No hit if the ray is parallel to the plane.
+        let denom = Vec3::dot(&self.normal, &r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        // Return None if the hit point parameter t is outside the ray interval.
+        let t = (self.d - Vec3::dot(&self.normal, &r.origin())) / denom;
+        if (t <= ray_t.start) || (ray_t.end <= t) {
+            return None;
+        }
+
+        // Determine the hit point lies within the planar shape using its plane coordinates.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = Vec3::dot(&self.w, &Vec3::cross(&planar_hitpt_vector, &self.v));
+        let beta = Vec3::dot(&self.w, &Vec3::cross(&self.u, &planar_hitpt_vector));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let (front_face, normal) = face_normal(r, self.normal);
+
+        Some(HitRecord {
+            p: intersection,
+            normal,
+            material: &self.material,
+            t,
+            u: alpha,
+            v: beta,
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        // Convert the uniform density over the area to a density over solid angle.
+        let ray_t = Range {
+            start: 0.001,
+            end: f32::INFINITY,
+        };
+        let Some(rec) = self.hit(&Ray::new(origin, direction), ray_t, 0) else {
+            return 0.0;
+        };
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (Vec3::dot(&direction, &self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let p = self.q + (random::<f32>() * self.u) + (random::<f32>() * self.v);
+        p - origin
+    }
+}
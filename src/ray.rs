@@ -4,11 +4,20 @@ use crate::vec3::Vec3;
 pub struct Ray {
     orig: Vec3,
     dir: Vec3,
+    tm: f32, // Time the ray was sent at, for objects moving while the shutter is open
 }
 
 impl Ray {
-    pub fn ray(a: Vec3, b: Vec3) -> Ray {
-        Ray { orig: a, dir: b }
+    pub fn new(a: Vec3, b: Vec3) -> Ray {
+        Ray::with_time(a, b, 0.0)
+    }
+
+    pub fn with_time(a: Vec3, b: Vec3, time: f32) -> Ray {
+        Ray {
+            orig: a,
+            dir: b,
+            tm: time,
+        }
     }
 
     pub fn origin(self) -> Vec3 {
@@ -19,6 +28,10 @@ impl Ray {
         self.dir
     }
 
+    pub fn time(self) -> f32 {
+        self.tm
+    }
+
     pub fn at(self, t: f32) -> Vec3 {
         self.orig + self.dir * t
     }
@@ -0,0 +1,1315 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use toml::Spanned;
+
+use crate::aabb::Aabb;
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::capsule::Capsule;
+use crate::cone::Cone;
+use crate::csg::{Csg, CsgOp};
+use crate::cuboid::Cuboid;
+use crate::cylinder::Cylinder;
+use crate::daylight::Daylight;
+use crate::disk::Disk;
+use crate::environment::EnvironmentMap;
+use crate::gltf_scene::load_gltf;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::mat4::Mat4;
+use crate::material::Material;
+use crate::medium::{ConstantMedium, Fog};
+use crate::mesh::MeshError;
+use crate::microfacet::Metal;
+use crate::moving_sphere::MovingSphere;
+use crate::obj::load_obj;
+use crate::ply::load_ply;
+use crate::quad::Quad;
+use crate::sdf::{Sdf, SdfObject};
+use crate::sphere::Sphere;
+use crate::texture::{
+    CheckerTexture, FbmTexture, ImageTexture, MarbleTexture, SolidColor, Texture, WoodTexture,
+};
+use crate::torus::Torus;
+use crate::transform::{AnimatedTransform, Transform};
+use crate::triangle::Triangle;
+use crate::vec3::Vec3;
+use crate::volume::{DensityField, HeterogeneousMedium, NoiseDensity, VoxelGrid};
+
+// A scene file is TOML with a [camera] table, a [textures.<name>] and [materials.<name>] table
+// per named texture and material, an [[objects]] entry per object and a [[lights]] entry per
+// point, spot or directional light, e.g.
+//
+//     [camera]
+//     image_width = 400
+//     lookfrom = [13.0, 2.0, 3.0]
+//
+//     [textures.checker]
+//     type = "checker"
+//     scale = 0.32
+//     even = [0.2, 0.3, 0.1]
+//     odd = [0.9, 0.9, 0.9]
+//
+//     [materials.ground]
+//     type = "lambertian"
+//     albedo = "checker"
+//
+//     [[objects]]
+//     type = "sphere"
+//     center = [0.0, -1000.0, 0.0]
+//     radius = 1000.0
+//     material = "ground"
+//
+//     [[lights]]
+//     type = "point"
+//     position = [0.0, 5.0, 0.0]
+//     intensity = [20.0, 20.0, 20.0]
+
+pub struct Scene {
+    pub camera: Camera,
+    pub world: HittableList,
+    pub lights: HittableList, // Emissive objects, sampled directly by the integrator
+    pub delta_lights: Vec<Box<dyn Light>>, // Point, spot and directional lights
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnknownMaterial {
+        line: usize,
+        name: String,
+    },
+    UnknownTexture {
+        line: usize,
+        name: String,
+    },
+    Image {
+        line: usize,
+        path: PathBuf,
+        err: image::ImageError,
+    },
+    Mesh {
+        line: usize,
+        path: PathBuf,
+        err: MeshError,
+    },
+    Voxels {
+        line: usize,
+        path: PathBuf,
+        err: io::Error,
+    },
+    Gltf(MeshError),
+    SingularTransform {
+        line: usize,
+    },
+    MediumMaterial {
+        line: usize,
+    },
+    UnboundedSdf {
+        line: usize,
+    },
+    NotPositive {
+        line: usize,
+        field: &'static str,
+    },
+    FieldOfView {
+        line: usize,
+    },
+    SpotAngle {
+        line: usize,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "could not read scene file: {}", err),
+            SceneError::Parse(err) => write!(f, "invalid scene file: {}", err),
+            SceneError::UnknownMaterial { line, name } => {
+                write!(f, "line {}: unknown material `{}`", line, name)
+            }
+            SceneError::UnknownTexture { line, name } => {
+                write!(f, "line {}: unknown texture `{}`", line, name)
+            }
+            SceneError::Image { line, path, err } => {
+                write!(
+                    f,
+                    "line {}: could not load {}: {}",
+                    line,
+                    path.display(),
+                    err
+                )
+            }
+            SceneError::Voxels { line, path, err } => {
+                write!(
+                    f,
+                    "line {}: could not load {}: {}",
+                    line,
+                    path.display(),
+                    err
+                )
+            }
+            SceneError::Gltf(err) => write!(f, "invalid glTF scene: {}", err),
+            SceneError::SingularTransform { line } => {
+                write!(f, "line {}: transform is not invertible", line)
+            }
+            SceneError::MediumMaterial { line } => {
+                write!(
+                    f,
+                    "line {}: objects with a density need an isotropic material",
+                    line
+                )
+            }
+            SceneError::UnboundedSdf { line } => {
+                write!(f, "line {}: repeated distance functions need bounds", line)
+            }
+            SceneError::NotPositive { line, field } => {
+                write!(f, "line {}: `{}` must be positive", line, field)
+            }
+            SceneError::SpotAngle { line } => {
+                let limits = "`angle` within 0 to 180 degrees and `falloff` within the angle";
+                write!(f, "line {}: spot lights need {}", line, limits)
+            }
+            SceneError::FieldOfView { line } => {
+                write!(f, "line {}: `vfov` must be between 0 and 180 degrees", line)
+            }
+            SceneError::Mesh { line, path, err } => {
+                write!(
+                    f,
+                    "line {}: could not load {}: {}",
+                    line,
+                    path.display(),
+                    err
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    #[serde(default)]
+    camera: CameraDesc,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectEntry>>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightDesc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CameraDesc {
+    aspect_ratio: Spanned<f32>,
+    image_width: Spanned<i32>,
+    samples_per_pixel: Spanned<i32>,
+    #[serde(alias = "max_depth")]
+    max_deph: Spanned<i32>,
+    vfov: Spanned<f32>,
+    lookfrom: [f32; 3],
+    lookat: [f32; 3],
+    vup: [f32; 3],
+    defocus_angle: f32,
+    focus_dist: f32,
+    shutter: [f32; 2], // Open and close time
+    threads: usize,
+    background: Option<Spanned<BackgroundDesc>>,
+    fog: Option<FogDesc>,
+}
+
+// The background is the sky gradient, a constant color, an environment map or a physical sky.
+// Without one, it's the sky gradient.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDesc {
+    Sky,
+    Color {
+        color: [f32; 3],
+    },
+    Environment {
+        path: PathBuf, // Equirectangular .hdr or .exr image
+        #[serde(default = "default_strength")]
+        strength: f32,
+        #[serde(default)]
+        rotation: f32, // Degrees around the y axis
+    },
+    Daylight {
+        sun_elevation: f32, // Degrees above the horizon
+        #[serde(default)]
+        sun_azimuth: f32, // Degrees around the y axis from -z towards +x
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        #[serde(default = "default_strength")]
+        strength: f32,
+    },
+}
+
+// Intensities are colors: the irradiance a point or spot light gives at a distance of 1, or a
+// directional light everywhere.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightDesc {
+    Point {
+        position: [f32; 3],
+        intensity: [f32; 3],
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        angle: f32, // Degrees from the axis to the edge of the cone
+        #[serde(default)]
+        falloff: f32, // Degrees inside the edge over which the light fades out
+        intensity: [f32; 3],
+    },
+    Directional {
+        direction: [f32; 3], // The way the light travels
+        intensity: [f32; 3],
+    },
+}
+
+impl LightDesc {
+    fn build(&self) -> Box<dyn Light> {
+        match self {
+            LightDesc::Point {
+                position,
+                intensity,
+            } => Box::new(PointLight::new(
+                Vec3::from(*position),
+                Vec3::from(*intensity),
+            )),
+            LightDesc::Spot {
+                position,
+                direction,
+                angle,
+                falloff,
+                intensity,
+            } => Box::new(SpotLight::new(
+                Vec3::from(*position),
+                Vec3::from(*direction),
+                (angle - falloff).to_radians(),
+                angle.to_radians(),
+                Vec3::from(*intensity),
+            )),
+            LightDesc::Directional {
+                direction,
+                intensity,
+            } => Box::new(DirectionalLight::new(
+                Vec3::from(*direction),
+                Vec3::from(*intensity),
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FogDesc {
+    density: f32,
+    #[serde(default = "white")]
+    albedo: [f32; 3],
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        CameraDesc {
+            aspect_ratio: Spanned::new(0..0, 1.0),
+            image_width: Spanned::new(0..0, 100),
+            samples_per_pixel: Spanned::new(0..0, 10),
+            max_deph: Spanned::new(0..0, 10),
+            vfov: Spanned::new(0..0, 90.0),
+            lookfrom: [0.0, 0.0, 0.0],
+            lookat: [0.0, 0.0, -1.0],
+            vup: [0.0, 1.0, 0.0],
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            shutter: [0.0, 1.0],
+            threads: 0,
+            background: None,
+            fog: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid {
+        color: [f32; 3],
+    },
+    Checker {
+        scale: f32,
+        even: [f32; 3],
+        odd: [f32; 3],
+    },
+    Image {
+        path: PathBuf, // Relative to the scene file
+    },
+    Fbm {
+        scale: f32,
+        #[serde(default = "default_octaves")]
+        octaves: i32,
+        #[serde(default = "white")]
+        color: [f32; 3],
+    },
+    Marble {
+        scale: f32,
+        #[serde(default = "white")]
+        color: [f32; 3],
+    },
+    Wood {
+        scale: f32,
+        light: [f32; 3],
+        dark: [f32; 3],
+    },
+}
+
+fn default_octaves() -> i32 {
+    7
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+// A material albedo is either a constant color or the name of a texture.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AlbedoDesc {
+    Color([f32; 3]),
+    Texture(String),
+}
+
+// A conductor's refractive index is the name of a metal or a complex index eta + ik with a
+// value for each of red, green and blue.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IorDesc {
+    Metal(MetalDesc),
+    Complex { eta: [f32; 3], k: [f32; 3] },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MetalDesc {
+    Gold,
+    Copper,
+    #[serde(alias = "aluminum")]
+    Aluminium,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: AlbedoDesc,
+    },
+    Metal {
+        albedo: AlbedoDesc,
+        fuzz: f32,
+    },
+    Dielectric {
+        ir: f32,
+    },
+    Conductor {
+        ior: IorDesc,
+        #[serde(default)]
+        roughness: f32,
+    },
+    RoughDielectric {
+        ir: f32,
+        roughness: f32,
+    },
+    DiffuseLight {
+        emit: [f32; 3],
+        #[serde(default)]
+        two_sided: bool,
+    },
+    Isotropic {
+        albedo: AlbedoDesc,
+    },
+}
+
+// An object's shape, and the transforms placing it in the world. Without `transform_end` the
+// object stays put, with it the object moves from `transform` at time 0 to `transform_end` at
+// time 1. With a `density` the object is a volume like smoke, filled with its isotropic
+// material.
+#[derive(Deserialize)]
+struct ObjectEntry {
+    #[serde(flatten)]
+    shape: ObjectDesc,
+    #[serde(default)]
+    transform: Vec<TransformDesc>,
+    transform_end: Option<Vec<TransformDesc>>,
+    density: Option<f32>, // Makes the shape the boundary of a constant density medium
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Quad {
+        q: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        material: String,
+    },
+    Triangle {
+        a: [f32; 3],
+        b: [f32; 3],
+        c: [f32; 3],
+        material: String,
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    MovingSphere {
+        center0: [f32; 3], // Center at time 0
+        center1: [f32; 3], // Center at time 1
+        radius: f32,
+        material: String,
+    },
+    Mesh {
+        path: PathBuf,            // Relative to the scene file
+        material: Option<String>, // Overrides the mesh's own materials
+    },
+    Box {
+        min: [f32; 3],
+        max: [f32; 3],
+        material: String,
+    },
+    Cylinder {
+        a: [f32; 3], // Centers of the ends
+        b: [f32; 3],
+        radius: f32,
+        #[serde(default)]
+        capped: bool,
+        material: String,
+    },
+    Cone {
+        base: [f32; 3], // Center of the base
+        apex: [f32; 3],
+        radius: f32,
+        #[serde(default)]
+        capped: bool,
+        material: String,
+    },
+    Capsule {
+        a: [f32; 3], // Ends of the segment in the middle
+        b: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Torus {
+        center: [f32; 3],
+        axis: [f32; 3],
+        major_radius: f32, // Of the circle through the middle of the tube
+        minor_radius: f32, // Of the tube
+        material: String,
+    },
+    Csg {
+        operation: CsgOpDesc,
+        a: Box<ObjectEntry>, // Objects inline, with their own transforms
+        b: Box<ObjectEntry>,
+    },
+    Sdf {
+        shape: SdfDesc,
+        bounds: Option<BoundsDesc>, // Needed around repeated shapes
+        material: String,
+    },
+    Volume {
+        min: [f32; 3], // Corners of the box the density field fills
+        max: [f32; 3],
+        field: FieldDesc,
+        absorption: f32, // Coefficients at a density of 1
+        scattering: f32,
+        #[serde(default)]
+        g: f32, // Henyey-Greenstein anisotropy, from -1 (backward) to 1 (forward)
+    },
+}
+
+// Distance function tree of an SDF object, e.g.
+// shape = { type = "smooth_union", k = 0.3, a = { type = "sphere", radius = 1.0 },
+//           b = { type = "box", center = [1.0, 0.0, 0.0], size = [1.0, 1.0, 1.0] } }
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum SdfDesc {
+    Sphere {
+        #[serde(default)]
+        center: [f32; 3],
+        radius: f32,
+    },
+    Box {
+        #[serde(default)]
+        center: [f32; 3],
+        size: [f32; 3],
+    },
+    Torus {
+        #[serde(default)]
+        center: [f32; 3],
+        major_radius: f32, // Around the y axis
+        minor_radius: f32,
+    },
+    Union {
+        a: Box<SdfDesc>,
+        b: Box<SdfDesc>,
+    },
+    SmoothUnion {
+        a: Box<SdfDesc>,
+        b: Box<SdfDesc>,
+        k: f32, // Distance over which the shapes blend
+    },
+    Repeat {
+        shape: Box<SdfDesc>,
+        period: [f32; 3], // 0 for axes without copies
+    },
+    Twist {
+        shape: Box<SdfDesc>,
+        rate: f32, // Degrees per unit of height, around the y axis
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoundsDesc {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl SdfDesc {
+    fn build(&self) -> Sdf {
+        match self {
+            SdfDesc::Sphere { center, radius } => Sdf::Sphere {
+                center: Vec3::from(*center),
+                radius: *radius,
+            },
+            SdfDesc::Box { center, size } => Sdf::Box {
+                center: Vec3::from(*center),
+                half_size: 0.5 * Vec3::from(*size),
+            },
+            SdfDesc::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => Sdf::Torus {
+                center: Vec3::from(*center),
+                major_radius: *major_radius,
+                minor_radius: *minor_radius,
+            },
+            SdfDesc::Union { a, b } => Sdf::Union {
+                a: Box::new(a.build()),
+                b: Box::new(b.build()),
+            },
+            SdfDesc::SmoothUnion { a, b, k } => Sdf::SmoothUnion {
+                a: Box::new(a.build()),
+                b: Box::new(b.build()),
+                k: *k,
+            },
+            SdfDesc::Repeat { shape, period } => Sdf::Repeat {
+                sdf: Box::new(shape.build()),
+                period: Vec3::from(*period),
+            },
+            SdfDesc::Twist { shape, rate } => Sdf::Twist {
+                sdf: Box::new(shape.build()),
+                rate: rate.to_radians(),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CsgOpDesc {
+    Union,
+    Intersection,
+    Difference,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum FieldDesc {
+    Voxels {
+        path: PathBuf, // Raw little endian floats, relative to the scene file
+        resolution: [usize; 3],
+    },
+    Noise {
+        scale: f32,
+        #[serde(default = "default_octaves")]
+        octaves: i32,
+    },
+}
+
+// Transforms of an object, applied in the order they are listed, e.g.
+// transform = [{ scale = 2.0 }, { rotate_y = 45.0 }, { translate = [1.0, 0.0, 0.0] }]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum TransformDesc {
+    Translate([f32; 3]),
+    RotateX(f32), // Degrees
+    RotateY(f32),
+    RotateZ(f32),
+    Scale(ScaleDesc),
+    Matrix([[f32; 4]; 4]), // Affine, row by row
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDesc {
+    Uniform(f32),
+    Axes([f32; 3]),
+}
+
+impl TransformDesc {
+    fn matrix(&self) -> Mat4 {
+        match *self {
+            TransformDesc::Translate(offset) => Mat4::translation(Vec3::from(offset)),
+            TransformDesc::RotateX(degrees) => Mat4::rotation_x(degrees),
+            TransformDesc::RotateY(degrees) => Mat4::rotation_y(degrees),
+            TransformDesc::RotateZ(degrees) => Mat4::rotation_z(degrees),
+            TransformDesc::Scale(ScaleDesc::Uniform(factor)) => {
+                Mat4::scaling(Vec3::new(factor, factor, factor))
+            }
+            TransformDesc::Scale(ScaleDesc::Axes(factors)) => Mat4::scaling(Vec3::from(factors)),
+            TransformDesc::Matrix(rows) => Mat4::new(rows),
+        }
+    }
+}
+
+impl ObjectDesc {
+    fn material(&self) -> Option<&str> {
+        match self {
+            ObjectDesc::Sphere { material, .. }
+            | ObjectDesc::Quad { material, .. }
+            | ObjectDesc::Triangle { material, .. }
+            | ObjectDesc::Disk { material, .. }
+            | ObjectDesc::MovingSphere { material, .. }
+            | ObjectDesc::Box { material, .. }
+            | ObjectDesc::Cylinder { material, .. }
+            | ObjectDesc::Cone { material, .. }
+            | ObjectDesc::Capsule { material, .. }
+            | ObjectDesc::Torus { material, .. }
+            | ObjectDesc::Sdf { material, .. } => Some(material),
+            ObjectDesc::Mesh { material, .. } => material.as_deref(),
+            ObjectDesc::Csg { .. } | ObjectDesc::Volume { .. } => None,
+        }
+    }
+}
+
+impl CameraDesc {
+    fn build(&self) -> Camera {
+        let mut cam = Camera::default();
+        cam.aspect_ratio = *self.aspect_ratio.get_ref();
+        cam.image_width = *self.image_width.get_ref();
+        cam.samples_per_pixel = *self.samples_per_pixel.get_ref();
+        cam.max_deph = *self.max_deph.get_ref();
+        cam.vfov = *self.vfov.get_ref();
+        cam.lookfrom = Vec3::from(self.lookfrom);
+        cam.lookat = Vec3::from(self.lookat);
+        cam.vup = Vec3::from(self.vup);
+        cam.defocus_angle = self.defocus_angle;
+        cam.focus_dist = self.focus_dist;
+        [cam.shutter_open, cam.shutter_close] = self.shutter;
+        cam.threads = self.threads;
+        cam.fog = self
+            .fog
+            .as_ref()
+            .map(|fog| Fog::new(fog.density, Vec3::from(fog.albedo)));
+        cam
+    }
+}
+
+struct Builder<'a> {
+    src: &'a str,
+    base_dir: &'a Path,
+    textures: HashMap<&'a str, Arc<dyn Texture>>,
+    materials: HashMap<&'a str, Material>,
+    meshes: HashMap<(&'a Path, Option<&'a str>), Arc<dyn Hittable>>, // Loaded mesh files
+}
+
+impl<'a> Builder<'a> {
+    fn texture(&self, desc: &Spanned<TextureDesc>) -> Result<Arc<dyn Texture>, SceneError> {
+        let line = self.line(desc.span().start);
+        match desc.get_ref() {
+            TextureDesc::Solid { color } => Ok(Arc::new(SolidColor::new(Vec3::from(*color)))),
+            TextureDesc::Checker { scale, even, odd } => {
+                // Subnormal scales too, whose reciprocal is infinite.
+                if !(scale.is_normal() && *scale > 0.0) {
+                    let field = "scale";
+                    return Err(SceneError::NotPositive { line, field });
+                }
+                Ok(Arc::new(CheckerTexture::new(
+                    *scale,
+                    Arc::new(SolidColor::new(Vec3::from(*even))),
+                    Arc::new(SolidColor::new(Vec3::from(*odd))),
+                )))
+            }
+            TextureDesc::Image { path } => {
+                let path = self.base_dir.join(path);
+                match ImageTexture::load(&path) {
+                    Ok(texture) => Ok(Arc::new(texture)),
+                    Err(err) => Err(SceneError::Image { line, path, err }),
+                }
+            }
+            TextureDesc::Fbm {
+                scale,
+                octaves,
+                color,
+            } => Ok(Arc::new(FbmTexture::new(
+                *scale,
+                *octaves,
+                Vec3::from(*color),
+            ))),
+            TextureDesc::Marble { scale, color } => {
+                Ok(Arc::new(MarbleTexture::new(*scale, Vec3::from(*color))))
+            }
+            TextureDesc::Wood { scale, light, dark } => Ok(Arc::new(WoodTexture::new(
+                *scale,
+                Vec3::from(*light),
+                Vec3::from(*dark),
+            ))),
+        }
+    }
+
+    fn background(&self, desc: &Spanned<BackgroundDesc>) -> Result<Background, SceneError> {
+        match desc.get_ref() {
+            BackgroundDesc::Sky => Ok(Background::Sky),
+            BackgroundDesc::Color { color } => Ok(Background::Color(Vec3::from(*color))),
+            BackgroundDesc::Environment {
+                path,
+                strength,
+                rotation,
+            } => {
+                let path = self.base_dir.join(path);
+                match EnvironmentMap::load(&path) {
+                    Ok(mut map) => {
+                        map.strength = *strength;
+                        map.rotation = rotation.to_radians();
+                        Ok(Background::Environment(Arc::new(map)))
+                    }
+                    Err(err) => Err(SceneError::Image {
+                        line: self.line(desc.span().start),
+                        path,
+                        err,
+                    }),
+                }
+            }
+            BackgroundDesc::Daylight {
+                sun_elevation,
+                sun_azimuth,
+                turbidity,
+                strength,
+            } => {
+                let mut daylight = Daylight::new(
+                    sun_elevation.to_radians(),
+                    sun_azimuth.to_radians(),
+                    *turbidity,
+                );
+                daylight.strength = *strength;
+                Ok(Background::Daylight(daylight))
+            }
+        }
+    }
+
+    fn albedo(&self, desc: &AlbedoDesc, line: usize) -> Result<Arc<dyn Texture>, SceneError> {
+        match desc {
+            AlbedoDesc::Color(color) => Ok(Arc::new(SolidColor::new(Vec3::from(*color)))),
+            AlbedoDesc::Texture(name) => {
+                self.textures.get(name.as_str()).cloned().ok_or_else(|| {
+                    SceneError::UnknownTexture {
+                        line,
+                        name: name.clone(),
+                    }
+                })
+            }
+        }
+    }
+
+    fn build_material(&self, desc: &Spanned<MaterialDesc>) -> Result<Material, SceneError> {
+        let line = self.line(desc.span().start);
+        match desc.get_ref() {
+            MaterialDesc::Lambertian { albedo } => Ok(Material::Lambertian {
+                albedo: self.albedo(albedo, line)?,
+            }),
+            MaterialDesc::Metal { albedo, fuzz } => Ok(Material::Metal {
+                albedo: self.albedo(albedo, line)?,
+                fuzz: *fuzz,
+            }),
+            MaterialDesc::Dielectric { ir } => Ok(Material::Dielectric { ir: *ir }),
+            MaterialDesc::Conductor { ior, roughness } => {
+                let (eta, k) = match ior {
+                    IorDesc::Metal(MetalDesc::Gold) => Metal::Gold.ior(),
+                    IorDesc::Metal(MetalDesc::Copper) => Metal::Copper.ior(),
+                    IorDesc::Metal(MetalDesc::Aluminium) => Metal::Aluminium.ior(),
+                    IorDesc::Complex { eta, k } => (Vec3::from(*eta), Vec3::from(*k)),
+                };
+                Ok(Material::Conductor {
+                    eta,
+                    k,
+                    roughness: *roughness,
+                })
+            }
+            MaterialDesc::RoughDielectric { ir, roughness } => Ok(Material::RoughDielectric {
+                ir: *ir,
+                roughness: *roughness,
+            }),
+            MaterialDesc::DiffuseLight { emit, two_sided } => Ok(Material::DiffuseLight {
+                emit: Vec3::from(*emit),
+                two_sided: *two_sided,
+            }),
+            MaterialDesc::Isotropic { albedo } => Ok(Material::Isotropic {
+                albedo: self.albedo(albedo, line)?,
+            }),
+        }
+    }
+
+    fn material(&self, name: &str, line: usize) -> Result<Material, SceneError> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::UnknownMaterial {
+                line,
+                name: name.to_string(),
+            })
+    }
+
+    fn is_light(&self, entry: &ObjectEntry) -> bool {
+        // Only these shapes can be sampled as lights, random bounces still find the others.
+        if !matches!(
+            entry.shape,
+            ObjectDesc::Sphere { .. }
+                | ObjectDesc::Quad { .. }
+                | ObjectDesc::Triangle { .. }
+                | ObjectDesc::Disk { .. }
+        ) {
+            return false;
+        }
+        if entry.transform_end.is_some() {
+            return false;
+        }
+        matches!(
+            entry
+                .shape
+                .material()
+                .and_then(|name| self.materials.get(name)),
+            Some(Material::DiffuseLight { .. })
+        )
+    }
+
+    fn light(&self, desc: &Spanned<LightDesc>) -> Result<Box<dyn Light>, SceneError> {
+        if let LightDesc::Spot { angle, falloff, .. } = *desc.get_ref() {
+            if !(angle > 0.0 && angle < 180.0 && (0.0..=angle).contains(&falloff)) {
+                let line = self.line(desc.span().start);
+                return Err(SceneError::SpotAngle { line });
+            }
+        }
+        Ok(desc.get_ref().build())
+    }
+
+    fn check_camera(&self, desc: &CameraDesc) -> Result<(), SceneError> {
+        // Settings that would otherwise fail only once rendering, like an empty or endlessly
+        // tall image, or pixels without samples.
+        let positive = [
+            (
+                "image_width",
+                desc.image_width.span(),
+                *desc.image_width.get_ref() >= 1,
+            ),
+            (
+                "samples_per_pixel",
+                desc.samples_per_pixel.span(),
+                *desc.samples_per_pixel.get_ref() >= 1,
+            ),
+            (
+                "max_depth",
+                desc.max_deph.span(),
+                *desc.max_deph.get_ref() >= 1,
+            ),
+            (
+                "aspect_ratio",
+                desc.aspect_ratio.span(),
+                desc.aspect_ratio.get_ref().is_finite() && *desc.aspect_ratio.get_ref() > 0.0,
+            ),
+        ];
+        for (field, span, valid) in positive {
+            if !valid {
+                let line = self.line(span.start);
+                return Err(SceneError::NotPositive { line, field });
+            }
+        }
+        let vfov = *desc.vfov.get_ref();
+        if !(vfov > 0.0 && vfov < 180.0) {
+            let line = self.line(desc.vfov.span().start);
+            return Err(SceneError::FieldOfView { line });
+        }
+        Ok(())
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.src[..offset].matches('\n').count() + 1
+    }
+
+    fn object(&mut self, entry: &'a Spanned<ObjectEntry>) -> Result<Box<dyn Hittable>, SceneError> {
+        let line = self.line(entry.span().start);
+        self.entry(entry.get_ref(), line)
+    }
+
+    fn entry(
+        &mut self,
+        entry: &'a ObjectEntry,
+        line: usize,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let object = self.placed_shape(entry, line)?;
+        let Some(density) = entry.density else {
+            return Ok(object);
+        };
+
+        // The shape bounds a medium scattering with the albedo of its isotropic material.
+        let material = match entry.shape.material() {
+            Some(name) => Some(self.material(name, line)?),
+            None => None,
+        };
+        match material {
+            Some(Material::Isotropic { albedo }) => {
+                Ok(Box::new(ConstantMedium::new(object, density, albedo)))
+            }
+            _ => Err(SceneError::MediumMaterial { line }),
+        }
+    }
+
+    fn placed_shape(
+        &mut self,
+        entry: &'a ObjectEntry,
+        line: usize,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let desc = &entry.shape;
+
+        // Objects loading the same mesh file with the same material share one copy of the mesh,
+        // each placed by its own transform.
+        if let ObjectDesc::Mesh {
+            ref path,
+            ref material,
+            ..
+        } = *desc
+        {
+            let key = (path.as_path(), material.as_deref());
+            let mesh = match self.meshes.get(&key) {
+                Some(mesh) => mesh.clone(),
+                None => {
+                    let mesh: Arc<dyn Hittable> = Arc::from(self.shape(desc, line)?);
+                    self.meshes.insert(key, mesh.clone());
+                    mesh
+                }
+            };
+            return self.place(mesh, entry, line);
+        }
+
+        // Operands of a boolean are full objects, built like any other.
+        let shape = match *desc {
+            ObjectDesc::Csg {
+                ref operation,
+                ref a,
+                ref b,
+            } => {
+                let op = match operation {
+                    CsgOpDesc::Union => CsgOp::Union,
+                    CsgOpDesc::Intersection => CsgOp::Intersection,
+                    CsgOpDesc::Difference => CsgOp::Difference,
+                };
+                let a = self.entry(a, line)?;
+                let b = self.entry(b, line)?;
+                Box::new(Csg::new(op, a, b))
+            }
+            _ => self.shape(desc, line)?,
+        };
+        if entry.transform.is_empty() && entry.transform_end.is_none() {
+            Ok(shape)
+        } else {
+            self.place(shape, entry, line)
+        }
+    }
+
+    fn place(
+        &self,
+        object: impl Into<Arc<dyn Hittable>>,
+        entry: &ObjectEntry,
+        line: usize,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        let start = self.matrix(&entry.transform, line)?;
+        match entry.transform_end {
+            Some(ref end) => {
+                let end = self.matrix(end, line)?;
+                Ok(Box::new(AnimatedTransform::new(
+                    object, start, end, 0.0, 1.0,
+                )))
+            }
+            None => Ok(Box::new(Transform::new(object, start))),
+        }
+    }
+
+    fn matrix(&self, transforms: &[TransformDesc], line: usize) -> Result<Mat4, SceneError> {
+        let matrix = transforms
+            .iter()
+            .fold(Mat4::identity(), |matrix, transform| {
+                transform.matrix() * matrix
+            });
+        match matrix.inverse() {
+            Some(_) => Ok(matrix),
+            None => Err(SceneError::SingularTransform { line }),
+        }
+    }
+
+    fn shape(&self, desc: &ObjectDesc, line: usize) -> Result<Box<dyn Hittable>, SceneError> {
+        let material = match desc.material() {
+            Some(name) => Some(self.material(name, line)?),
+            None => None,
+        };
+        match *desc {
+            ObjectDesc::Sphere { center, radius, .. } => Ok(Box::new(Sphere::new(
+                Vec3::from(center),
+                radius,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Quad { q, u, v, .. } => Ok(Box::new(Quad::new(
+                Vec3::from(q),
+                Vec3::from(u),
+                Vec3::from(v),
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Triangle { a, b, c, .. } => Ok(Box::new(Triangle::new(
+                Vec3::from(a),
+                Vec3::from(b),
+                Vec3::from(c),
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Disk {
+                center,
+                normal,
+                radius,
+                ..
+            } => Ok(Box::new(Disk::new(
+                Vec3::from(center),
+                Vec3::from(normal),
+                radius,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::MovingSphere {
+                center0,
+                center1,
+                radius,
+                ..
+            } => Ok(Box::new(MovingSphere::new(
+                Vec3::from(center0),
+                Vec3::from(center1),
+                0.0,
+                1.0,
+                radius,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Mesh { ref path, .. } => {
+                let path = self.base_dir.join(path);
+                // PLY files by extension, anything else is read as OBJ.
+                let mesh = match path.extension().and_then(|e| e.to_str()) {
+                    Some(ext) if ext.eq_ignore_ascii_case("ply") => load_ply(&path, material),
+                    _ => load_obj(&path, material),
+                };
+                match mesh {
+                    Ok(mesh) => Ok(Box::new(mesh)),
+                    Err(err) => Err(SceneError::Mesh { line, path, err }),
+                }
+            }
+            ObjectDesc::Box { min, max, .. } => Ok(Box::new(Cuboid::new(
+                Vec3::from(min),
+                Vec3::from(max),
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Cylinder {
+                a,
+                b,
+                radius,
+                capped,
+                ..
+            } => Ok(Box::new(Cylinder::new(
+                Vec3::from(a),
+                Vec3::from(b),
+                radius,
+                capped,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Cone {
+                base,
+                apex,
+                radius,
+                capped,
+                ..
+            } => Ok(Box::new(Cone::new(
+                Vec3::from(base),
+                Vec3::from(apex),
+                radius,
+                capped,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Capsule { a, b, radius, .. } => Ok(Box::new(Capsule::new(
+                Vec3::from(a),
+                Vec3::from(b),
+                radius,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+                ..
+            } => Ok(Box::new(Torus::new(
+                Vec3::from(center),
+                Vec3::from(axis),
+                major_radius,
+                minor_radius,
+                material.unwrap_or_default(),
+            ))),
+            ObjectDesc::Sdf {
+                ref shape,
+                ref bounds,
+                ..
+            } => {
+                let sdf = shape.build();
+                let bounds = match bounds {
+                    Some(bounds) => Aabb::new(Vec3::from(bounds.min), Vec3::from(bounds.max)),
+                    None => sdf.bounds(),
+                };
+                let finite = |v: Vec3| v.x().is_finite() && v.y().is_finite() && v.z().is_finite();
+                if !finite(bounds.minimum) || !finite(bounds.maximum) {
+                    return Err(SceneError::UnboundedSdf { line });
+                }
+                Ok(Box::new(SdfObject::with_bounds(
+                    sdf,
+                    bounds,
+                    material.unwrap_or_default(),
+                )))
+            }
+            ObjectDesc::Csg { .. } => unreachable!("booleans are built from their objects"),
+            ObjectDesc::Volume {
+                min,
+                max,
+                ref field,
+                absorption,
+                scattering,
+                g,
+            } => {
+                let field: Box<dyn DensityField> = match *field {
+                    FieldDesc::Voxels {
+                        ref path,
+                        resolution,
+                    } => {
+                        let path = self.base_dir.join(path);
+                        match VoxelGrid::load(&path, resolution) {
+                            Ok(grid) => Box::new(grid),
+                            Err(err) => return Err(SceneError::Voxels { line, path, err }),
+                        }
+                    }
+                    FieldDesc::Noise { scale, octaves } => {
+                        Box::new(NoiseDensity::new(scale, octaves))
+                    }
+                };
+                Ok(Box::new(HeterogeneousMedium::new(
+                    Aabb::new(Vec3::from(min), Vec3::from(max)),
+                    field,
+                    absorption,
+                    scattering,
+                    g,
+                )))
+            }
+        }
+    }
+}
+
+pub fn parse_scene(src: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    // Files referenced by the scene, like image textures, are looked up relative to `base_dir`.
+    let desc: SceneDesc = toml::from_str(src).map_err(SceneError::Parse)?;
+
+    let mut builder = Builder {
+        src,
+        base_dir,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        meshes: HashMap::new(),
+    };
+    for (name, texture) in &desc.textures {
+        let texture = builder.texture(texture)?;
+        builder.textures.insert(name, texture);
+    }
+    for (name, material) in &desc.materials {
+        let material = builder.build_material(material)?;
+        builder.materials.insert(name, material);
+    }
+
+    let mut world = HittableList::default();
+    let mut lights = HittableList::default();
+    for object in &desc.objects {
+        let is_light = builder.is_light(object.get_ref());
+        let object = builder.object(object)?;
+        if is_light {
+            // Lights are also in the world, for the rays that hit them at random.
+            let object: Arc<dyn Hittable> = Arc::from(object);
+            lights.add(Box::new(object.clone()));
+            world.add(Box::new(object));
+        } else {
+            world.add(object);
+        }
+    }
+
+    let delta_lights = desc
+        .lights
+        .iter()
+        .map(|light| builder.light(light))
+        .collect::<Result<_, _>>()?;
+
+    builder.check_camera(&desc.camera)?;
+    let mut camera = desc.camera.build();
+    if let Some(background) = &desc.camera.background {
+        camera.background = builder.background(background)?;
+    }
+
+    Ok(Scene {
+        camera,
+        world,
+        lights,
+        delta_lights,
+    })
+}
+
+pub fn load_scene(path: &Path) -> Result<Scene, SceneError> {
+    // glTF scenes by extension, anything else is read as a TOML scene file.
+    if let Some("gltf" | "glb") = path.extension().and_then(|e| e.to_str()) {
+        return load_gltf(path, CameraDesc::default().build()).map_err(SceneError::Gltf);
+    }
+    let src = fs::read_to_string(path).map_err(SceneError::Io)?;
+    parse_scene(&src, path.parent().unwrap_or(Path::new("")))
+}
@@ -0,0 +1,356 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::sphere::get_sphere_uv;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+const MAX_STEPS: usize = 512;
+
+// Tree of signed distance functions: negative inside, positive outside, and at most the
+// distance to the surface.
+#[derive(Debug, Clone)]
+pub enum Sdf {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    Box {
+        center: Vec3,
+        half_size: Vec3,
+    },
+    Torus {
+        center: Vec3,
+        major_radius: f32, // Around the y axis
+        minor_radius: f32,
+    },
+    Union {
+        a: Box<Sdf>,
+        b: Box<Sdf>,
+    },
+    // Union blending the shapes together where they come within k of each other.
+    SmoothUnion {
+        a: Box<Sdf>,
+        b: Box<Sdf>,
+        k: f32,
+    },
+    // Copies of the shape every period along each axis with a non-zero period, the original
+    // centered on the origin. The shape should fit in its cell.
+    Repeat {
+        sdf: Box<Sdf>,
+        period: Vec3,
+    },
+    // The shape rotated around the y axis by an angle growing with the height.
+    Twist {
+        sdf: Box<Sdf>,
+        rate: f32, // Radians per unit of height
+    },
+}
+
+impl Sdf {
+    pub fn distance(&self, p: Vec3) -> f32 {
+        match self {
+            Sdf::Sphere { center, radius } => (p - *center).length() - radius,
+            Sdf::Box { center, half_size } => {
+                let q = p - *center;
+                let q = Vec3::new(q.x().abs(), q.y().abs(), q.z().abs()) - *half_size;
+                let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0));
+                outside.length() + q.x().max(q.y()).max(q.z()).min(0.0)
+            }
+            Sdf::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let q = p - *center;
+                let radial = (q.x() * q.x() + q.z() * q.z()).sqrt() - major_radius;
+                (radial * radial + q.y() * q.y()).sqrt() - minor_radius
+            }
+            Sdf::Union { a, b } => a.distance(p).min(b.distance(p)),
+            Sdf::SmoothUnion { a, b, k } => {
+                // Polynomial smooth minimum.
+                let (da, db) = (a.distance(p), b.distance(p));
+                if *k <= 0.0 {
+                    return da.min(db);
+                }
+                let h = (k - (da - db).abs()).max(0.0) / k;
+                da.min(db) - h * h * k * 0.25
+            }
+            Sdf::Repeat { sdf, period } => {
+                let wrap = |x: f32, period: f32| {
+                    if period > 0.0 {
+                        x - period * (x / period).round()
+                    } else {
+                        x
+                    }
+                };
+                sdf.distance(Vec3::new(
+                    wrap(p.x(), period.x()),
+                    wrap(p.y(), period.y()),
+                    wrap(p.z(), period.z()),
+                ))
+            }
+            Sdf::Twist { sdf, rate } => {
+                // Undo the rotation at the point's height.
+                let (sin, cos) = (-rate * p.y()).sin_cos();
+                sdf.distance(Vec3::new(
+                    cos * p.x() - sin * p.z(),
+                    p.y(),
+                    sin * p.x() + cos * p.z(),
+                ))
+            }
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        // Infinite along the repeated axes.
+        match self {
+            Sdf::Sphere { center, radius } => {
+                let rvec = Vec3::new(*radius, *radius, *radius);
+                Aabb::new(*center - rvec, *center + rvec)
+            }
+            Sdf::Box { center, half_size } => Aabb::new(*center - *half_size, *center + *half_size),
+            Sdf::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let outer = major_radius + minor_radius;
+                let extent = Vec3::new(outer, *minor_radius, outer);
+                Aabb::new(*center - extent, *center + extent)
+            }
+            Sdf::Union { a, b } => Aabb::surrounding(&a.bounds(), &b.bounds()),
+            Sdf::SmoothUnion { a, b, k } => {
+                // The blend bulges out by at most k / 4.
+                let bbox = Aabb::surrounding(&a.bounds(), &b.bounds());
+                let pad = Vec3::new(0.25 * k, 0.25 * k, 0.25 * k);
+                Aabb::new(bbox.minimum - pad, bbox.maximum + pad)
+            }
+            Sdf::Repeat { sdf, period } => {
+                let bbox = sdf.bounds();
+                let extent = |axis: usize, bound: Vec3, infinity: f32| {
+                    if period[axis] > 0.0 {
+                        infinity
+                    } else {
+                        bound[axis]
+                    }
+                };
+                let (lo, hi) = (f32::NEG_INFINITY, f32::INFINITY);
+                Aabb::new(
+                    Vec3::new(
+                        extent(0, bbox.minimum, lo),
+                        extent(1, bbox.minimum, lo),
+                        extent(2, bbox.minimum, lo),
+                    ),
+                    Vec3::new(
+                        extent(0, bbox.maximum, hi),
+                        extent(1, bbox.maximum, hi),
+                        extent(2, bbox.maximum, hi),
+                    ),
+                )
+            }
+            Sdf::Twist { sdf, .. } => {
+                // Anywhere around the y axis, within the shape's largest distance from it.
+                let bbox = sdf.bounds();
+                let radius = horizontal_radius(&bbox);
+                Aabb::new(
+                    Vec3::new(-radius, bbox.minimum.y(), -radius),
+                    Vec3::new(radius, bbox.maximum.y(), radius),
+                )
+            }
+        }
+    }
+
+    fn lipschitz(&self, radius: f32) -> f32 {
+        // How much faster than the distance to the surface the function may change within
+        // `radius` of the y axis. Sphere tracing divides its steps by this to stay safe.
+        match self {
+            Sdf::Sphere { .. } | Sdf::Box { .. } | Sdf::Torus { .. } => 1.0,
+            Sdf::Union { a, b } | Sdf::SmoothUnion { a, b, .. } => {
+                a.lipschitz(radius).max(b.lipschitz(radius))
+            }
+            Sdf::Repeat { sdf, .. } => sdf.lipschitz(radius),
+            Sdf::Twist { sdf, rate } => {
+                (1.0 + (rate * radius).powi(2)).sqrt() * sdf.lipschitz(radius)
+            }
+        }
+    }
+}
+
+fn horizontal_radius(bbox: &Aabb) -> f32 {
+    // Largest distance of the box's points from the y axis.
+    let x = bbox.minimum.x().abs().max(bbox.maximum.x().abs());
+    let z = bbox.minimum.z().abs().max(bbox.maximum.z().abs());
+    (x * x + z * z).sqrt()
+}
+
+// Surface where a distance function is zero, found by sphere tracing: stepping along the ray
+// by the distance to the surface, which can't overshoot it, until close enough.
+pub struct SdfObject {
+    sdf: Sdf,
+    material: Material,
+    bbox: Aabb,
+    lipschitz: f32,
+    epsilon: f32, // Distance counting as on the surface
+}
+
+impl SdfObject {
+    pub fn new(sdf: Sdf, material: Material) -> SdfObject {
+        let bounds = sdf.bounds();
+        SdfObject::with_bounds(sdf, bounds, material)
+    }
+
+    // Traces only within the bounds, which must be finite, e.g. around repeated shapes.
+    pub fn with_bounds(sdf: Sdf, bounds: Aabb, material: Material) -> SdfObject {
+        let bbox = Aabb::overlap(&bounds, &sdf.bounds());
+        let lipschitz = sdf.lipschitz(horizontal_radius(&bbox));
+        // Small next to the shape and below the offset scattered rays start at, but above the
+        // rounding of the coordinates so the steps can get there.
+        let size = (bbox.maximum - bbox.minimum).length();
+        let reach = bbox.minimum.length().max(bbox.maximum.length());
+        let epsilon = (1e-4 * size).min(1e-4).max(1e-6 * reach);
+        SdfObject {
+            sdf,
+            material,
+            bbox,
+            lipschitz,
+            epsilon,
+        }
+    }
+
+    fn normal(&self, p: Vec3) -> Vec3 {
+        // Gradient of the distance by central differences.
+        let h = self.epsilon;
+        let gradient =
+            |axis: Vec3| self.sdf.distance(p + h * axis) - self.sdf.distance(p - h * axis);
+        Vec3::unit_vector(Vec3::new(
+            gradient(Vec3::new(1.0, 0.0, 0.0)),
+            gradient(Vec3::new(0.0, 1.0, 0.0)),
+            gradient(Vec3::new(0.0, 0.0, 1.0)),
+        ))
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Steps use the distance's magnitude, so rays inside the shape, like refracted ones,
+        // find their way out too. A ray starting on the surface, like one scattered off it,
+        // first steps clear of it so it doesn't hit the surface it's leaving.
+        let inside = self.bbox.interval(r, ray_t.clone())?;
+        let scale = self.lipschitz * r.direction().length();
+        let mut t = inside.start;
+        let mut leaving = inside.start == ray_t.start;
+        for _ in 0..MAX_STEPS {
+            let distance = self.sdf.distance(r.at(t)).abs();
+            if distance >= self.epsilon {
+                leaving = false;
+            } else if !leaving {
+                let p = r.at(t);
+                let outward_normal = self.normal(p);
+                let (front_face, normal) = face_normal(r, outward_normal);
+                let (u, v) = get_sphere_uv(outward_normal);
+                return Some(HitRecord {
+                    p,
+                    normal,
+                    material: &self.material,
+                    t,
+                    u,
+                    v,
+                    front_face,
+                    color: None,
+                });
+            }
+            t += distance.max(self.epsilon) / scale;
+            if t >= inside.end {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng;
+    use crate::sphere::Sphere;
+
+    fn assert_same_hit(sdf: &SdfObject, sphere: &Sphere, r: &Ray) -> Option<HitRecord<'static>> {
+        // Both hit or both miss, and the SDF's hit is the sphere's within the tolerance.
+        let range = 0.001..f32::INFINITY;
+        let expected = sphere.hit(r, range.clone(), 0);
+        let found = sdf.hit(r, range, 0);
+        match (&expected, &found) {
+            (None, None) => None,
+            (Some(expected), Some(found)) => {
+                assert!(
+                    (expected.t - found.t).abs() < 1e-3,
+                    "t {expected:?} {found:?}"
+                );
+                assert!((expected.p - found.p).length() < 1e-3, "p {found:?}");
+                assert!(Vec3::dot(&expected.normal, &found.normal) > 0.999);
+                assert_eq!(expected.front_face, found.front_face);
+                Some(HitRecord {
+                    material: &Material::Dielectric { ir: 1.5 },
+                    color: None,
+                    ..*found
+                })
+            }
+            _ => panic!("sphere {expected:?}, sdf {found:?} along {r:?}"),
+        }
+    }
+
+    fn check_against_sphere(sdf: &SdfObject) {
+        // Rays from all around at the unit sphere, then bouncing off it and refracting through.
+        let sphere = Sphere::new(Vec3::default(), 1.0, Material::Dielectric { ir: 1.5 });
+        rng::seed(7);
+        for _ in 0..1000 {
+            let origin = 3.0 * Vec3::random_unit_vector();
+            let target = 0.9 * Vec3::random_in_unit_sphere();
+            let r = Ray::new(origin, target - origin);
+            let rec = assert_same_hit(sdf, &sphere, &r).expect("ray aimed at the sphere missed");
+
+            let bounced = Vec3::reflect(r.direction(), rec.normal);
+            assert!(assert_same_hit(sdf, &sphere, &Ray::new(rec.p, bounced)).is_none());
+
+            let refracted = Vec3::unit_vector(-rec.normal + 0.5 * Vec3::random_in_unit_sphere());
+            let exit = assert_same_hit(sdf, &sphere, &Ray::new(rec.p, refracted))
+                .expect("ray refracted into the sphere didn't leave it");
+            assert!(!exit.front_face);
+            assert!(assert_same_hit(sdf, &sphere, &Ray::new(exit.p, refracted)).is_none());
+        }
+    }
+
+    #[test]
+    fn sphere_matches_analytic_sphere() {
+        let sdf = Sdf::Sphere {
+            center: Vec3::default(),
+            radius: 1.0,
+        };
+        check_against_sphere(&SdfObject::new(sdf, Material::Dielectric { ir: 1.5 }));
+    }
+
+    #[test]
+    fn sphere_in_large_bounds_matches_analytic_sphere() {
+        // A single copy of a repeated sphere, with bounds much larger than the shape.
+        let sdf = Sdf::Repeat {
+            sdf: Box::new(Sdf::Sphere {
+                center: Vec3::default(),
+                radius: 1.0,
+            }),
+            period: Vec3::new(100.0, 0.0, 100.0),
+        };
+        let bounds = Aabb::new(Vec3::new(-40.0, -40.0, -40.0), Vec3::new(40.0, 40.0, 40.0));
+        check_against_sphere(&SdfObject::with_bounds(
+            sdf,
+            bounds,
+            Material::Dielectric { ir: 1.5 },
+        ));
+    }
+}
@@ -1,10 +1,15 @@
+use std::f32::consts::PI;
 use std::ops::Range;
 
+use crate::aabb::Aabb;
 use crate::hittable::*;
 use crate::material::Material;
+use crate::onb::Onb;
+use crate::rng::random;
 use crate::vec3::Vec3;
 use crate::Ray;
 
+#[derive(Debug, Clone)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
@@ -14,51 +19,116 @@ pub struct Sphere {
 impl Sphere {
     pub fn new(center: Vec3, radius: f32, material: Material) -> Sphere {
         Sphere {
-            center: center,
-            radius: radius,
-            material: material,
+            center,
+            radius,
+            material,
         }
     }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord> {
-        let oc: Vec3 = r.origin() - self.center;
-        let a: f32 = r.direction().length_squared();
-        let half_b: f32 = Vec3::dot(&oc, &r.direction());
-        let c: f32 = oc.length_squared() - self.radius.powi(2);
-
-        let discriminant: f32 = half_b.powi(2) - a * c;
-        if discriminant < 0.0 {
-            return None;
-        }
-        let sqrtd: f32 = discriminant.sqrt();
-
-        // Find the nearest root that lies in the acceptable range.
-        let mut root: f32 = (-half_b - sqrtd) / a;
-        if (&root <= &ray_t.start) || (&ray_t.end <= &root) {
-            root = (-half_b + sqrtd) / a;
-            if (&root <= &ray_t.start) || (&ray_t.end <= &root) {
-                return None;
-            }
-        };
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        hit_sphere(self.center, self.radius, &self.material, r, ray_t)
+    }
 
-        // Sets the hit record normal vector.
-        // NOTE: the parameter `outward_normal` is assumed to have unit length.
-        let outward_normal = (r.at(root) - self.center) / self.radius;
-        let front_face = Vec3::dot(&r.direction(), &outward_normal) < 0.0;
-        let normal = if front_face {
-            outward_normal
-        } else {
-            -outward_normal
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - rvec, self.center + rvec)
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        // Uniform density over the cone of directions subtended by the sphere.
+        let ray_t = Range {
+            start: 0.001,
+            end: f32::INFINITY,
         };
+        if self.hit(&Ray::new(origin, direction), ray_t, 0).is_none() {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        let uvw = Onb::new(direction);
+        uvw.transform(random_to_sphere(self.radius, distance_squared))
+    }
+}
 
-        return Some(HitRecord {
-            p: r.at(root),
-            normal: normal,
-            material: self.material,
-            t: root,
-            front_face: front_face,
-        });
+pub(crate) fn hit_sphere<'a>(
+    center: Vec3,
+    radius: f32,
+    material: &'a Material,
+    r: &Ray,
+    ray_t: Range<f32>,
+) -> Option<HitRecord<'a>> {
+    let oc: Vec3 = r.origin() - center;
+    let a: f32 = r.direction().length_squared();
+    let half_b: f32 = Vec3::dot(&oc, &r.direction());
+    let c: f32 = oc.length_squared() - radius.powi(2);
+
+    let discriminant: f32 = half_b.powi(2) - a * c;
+    if discriminant < 0.0 {
+        return None;
     }
+    let sqrtd: f32 = discriminant.sqrt();
+
+    // Find the nearest root that lies in the acceptable range.
+    let mut root: f32 = (-half_b - sqrtd) / a;
+    if (root <= ray_t.start) || (ray_t.end <= root) {
+        root = (-half_b + sqrtd) / a;
+        if (root <= ray_t.start) || (ray_t.end <= root) {
+            return None;
+        }
+    };
+
+    // Sets the hit record normal vector.
+    let outward_normal = (r.at(root) - center) / radius;
+    let (front_face, normal) = face_normal(r, outward_normal);
+
+    let (u, v) = get_sphere_uv(outward_normal);
+
+    Some(HitRecord {
+        p: r.at(root),
+        normal,
+        material,
+        t: root,
+        u,
+        v,
+        front_face,
+        color: None,
+    })
+}
+
+pub(crate) fn get_sphere_uv(p: Vec3) -> (f32, f32) {
+    // p: a given point on the sphere of radius one, centered at the origin.
+    // u: returned value [0,1] of angle around the Y axis from X=-1.
+    // v: returned value [0,1] of angle from Y=-1 to Y=+1.
+    let theta = (-p.y()).acos();
+    let phi = (-p.z()).atan2(p.x()) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
+}
+
+fn random_to_sphere(radius: f32, distance_squared: f32) -> Vec3 {
+    // Random direction inside the cone around the z axis that a sphere of the given radius
+    // subtends from the given distance.
+    let r1 = random::<f32>();
+    let r2 = random::<f32>();
+    let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).max(0.0).sqrt() - 1.0);
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * (1.0 - z * z).sqrt();
+    let y = phi.sin() * (1.0 - z * z).sqrt();
+
+    Vec3::new(x, y, z)
 }
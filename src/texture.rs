@@ -0,0 +1,201 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::perlin::Perlin;
+use crate::vec3::Vec3;
+
+pub trait Texture: Send + Sync + fmt::Debug {
+    // Color of the texture at surface coordinates (u, v) and hit point p.
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SolidColor {
+    pub albedo: Vec3,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Vec3) -> SolidColor {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _p: Vec3) -> Vec3 {
+        self.albedo
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckerTexture {
+    inv_scale: f32,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f32, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> CheckerTexture {
+        CheckerTexture {
+            inv_scale: scale.recip(),
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f32, v: f32, p: Vec3) -> Vec3 {
+        // Solid checker pattern of cubes with sides of length `scale` in world space.
+        // The cube indices saturate far away, so they are summed wide enough not to overflow.
+        let x = (self.inv_scale * p.x()).floor() as i32 as i64;
+        let y = (self.inv_scale * p.y()).floor() as i32 as i64;
+        let z = (self.inv_scale * p.z()).floor() as i32 as i64;
+
+        if (x + y + z).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>, // Linear colors, row-major with the top row first
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> ImageTexture {
+        assert_eq!(pixels.len(), width * height);
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<ImageTexture, image::ImageError> {
+        let image = image::open(path)?.to_rgb32f();
+        // Image files store gamma-encoded colors. Undo the same gamma 2 transform the renderer
+        // applies on output, so an unlit texture renders back with its original colors.
+        let pixels = image
+            .pixels()
+            .map(|p| Vec3::new(p[0] * p[0], p[1] * p[1], p[2] * p[2]))
+            .collect();
+
+        Ok(ImageTexture::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+        ))
+    }
+}
+
+impl fmt::Debug for ImageTexture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImageTexture")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _p: Vec3) -> Vec3 {
+        if self.pixels.is_empty() {
+            return Vec3::new(0.0, 1.0, 1.0);
+        }
+
+        // Clamp input texture coordinates to [0,1] x [1,0], flipping v to image coordinates.
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FbmTexture {
+    noise: Perlin,
+    scale: f32,
+    octaves: i32,
+    color: Vec3,
+}
+
+impl FbmTexture {
+    pub fn new(scale: f32, octaves: i32, color: Vec3) -> FbmTexture {
+        FbmTexture {
+            noise: Perlin::new(),
+            scale,
+            octaves,
+            color,
+        }
+    }
+}
+
+impl Texture for FbmTexture {
+    fn value(&self, _u: f32, _v: f32, p: Vec3) -> Vec3 {
+        // Map the signed noise sum from about [-1, 1] to [0, 1].
+        let n = self.noise.fbm(self.scale * p, self.octaves);
+        (0.5 * (1.0 + n)).clamp(0.0, 1.0) * self.color
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f32,
+    color: Vec3,
+}
+
+impl MarbleTexture {
+    pub fn new(scale: f32, color: Vec3) -> MarbleTexture {
+        MarbleTexture {
+            noise: Perlin::new(),
+            scale,
+            color,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f32, _v: f32, p: Vec3) -> Vec3 {
+        // Veins from a sine wave along z, with its phase disturbed by turbulence.
+        let phase = self.scale * p.z() + 10.0 * self.noise.turbulence(p, 7);
+        0.5 * (1.0 + phase.sin()) * self.color
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WoodTexture {
+    noise: Perlin,
+    scale: f32,
+    light: Vec3,
+    dark: Vec3,
+}
+
+impl WoodTexture {
+    pub fn new(scale: f32, light: Vec3, dark: Vec3) -> WoodTexture {
+        WoodTexture {
+            noise: Perlin::new(),
+            scale,
+            light,
+            dark,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f32, _v: f32, p: Vec3) -> Vec3 {
+        // Growth rings around the y axis, warped by a little turbulence.
+        let radius = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        let rings = self.scale * radius + 2.0 * self.noise.turbulence(p, 4);
+        let t = rings - rings.floor();
+        (1.0 - t) * self.light + t * self.dark
+    }
+}
@@ -0,0 +1,258 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::disk::disk_bbox;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Ring around an axis through the center: the points at the minor radius from the circle of the
+// major radius around the axis.
+#[derive(Debug, Clone)]
+pub struct Torus {
+    pub center: Vec3,
+    pub axis: Vec3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub material: Material,
+    uvw: Onb, // Frame with w along the axis
+}
+
+impl Torus {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f32,
+        minor_radius: f32,
+        material: Material,
+    ) -> Torus {
+        let uvw = Onb::new(axis);
+        Torus {
+            center,
+            axis: uvw.w,
+            major_radius,
+            minor_radius,
+            material,
+            uvw,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Intersect in the torus' frame, with the axis along z, in double precision and with a
+        // unit direction, so s below is the distance along the ray.
+        let to_f64 = |v: Vec3| [v.x() as f64, v.y() as f64, v.z() as f64];
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let length = r.direction().length() as f64;
+        let o = to_f64(self.uvw.local(r.origin() - self.center));
+        let d = to_f64(self.uvw.local(r.direction())).map(|c| c / length);
+        let big_r = self.major_radius as f64;
+        let small_r = self.minor_radius as f64;
+
+        // Only the part of the ray inside the bounding sphere can hit. Moving the origin up to
+        // it keeps the quartic's coefficients, and its roots, well conditioned for far rays. The
+        // sphere touches the torus, so it's padded a little to start the search outside it.
+        let bound = 1.001 * (big_r + small_r);
+        let half_b = dot(o, d);
+        let discriminant = half_b * half_b - (dot(o, o) - bound * bound);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let s_min = (-half_b - discriminant.sqrt()).max(ray_t.start as f64 * length);
+        let s_max = (-half_b + discriminant.sqrt()).min(ray_t.end as f64 * length);
+        if s_min >= s_max {
+            return None;
+        }
+        let o = [0, 1, 2].map(|i| o[i] + s_min * d[i]);
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2), with p = o + s d, expanded in s.
+        let e = dot(o, o) + big_r * big_r - small_r * small_r;
+        let f = dot(o, d);
+        let four_r2 = 4.0 * big_r * big_r;
+        let coefficients = [
+            e * e - four_r2 * (o[0] * o[0] + o[1] * o[1]),
+            4.0 * e * f - 2.0 * four_r2 * (o[0] * d[0] + o[1] * d[1]),
+            2.0 * e + 4.0 * f * f - four_r2 * (d[0] * d[0] + d[1] * d[1]),
+            4.0 * f,
+            1.0,
+        ];
+        let s = polynomial_roots(&coefficients, 4, 0.0, s_max - s_min)
+            .as_slice()
+            .iter()
+            .map(|s| s_min + s)
+            .find(|&s| s > ray_t.start as f64 * length)?;
+        let t = (s / length) as f32;
+        if t >= ray_t.end {
+            return None;
+        }
+
+        // The normal points away from the closest point of the central circle. u goes around
+        // the axis and v around the tube.
+        let p = Vec3::new(
+            (o[0] + (s - s_min) * d[0]) as f32,
+            (o[1] + (s - s_min) * d[1]) as f32,
+            (o[2] + (s - s_min) * d[2]) as f32,
+        );
+        let radial = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let ring = if radial > 0.0 {
+            Vec3::new(p.x(), p.y(), 0.0) * (self.major_radius / radial)
+        } else {
+            Vec3::default()
+        };
+        let normal = Vec3::unit_vector(p - ring);
+        let (front_face, normal) = face_normal(r, self.uvw.transform(normal));
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            material: &self.material,
+            t,
+            u: (p.y().atan2(p.x()) + PI) / (2.0 * PI),
+            v: (p.z().atan2(radial - self.major_radius) + PI) / (2.0 * PI),
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // The central circle's box, grown by the tube.
+        let circle = disk_bbox(self.center, self.axis, self.major_radius);
+        let rvec = Vec3::new(self.minor_radius, self.minor_radius, self.minor_radius);
+        Aabb::new(circle.minimum - rvec, circle.maximum + rvec)
+    }
+}
+
+fn polynomial_roots(coefficients: &[f64; 5], degree: usize, lo: f64, hi: f64) -> Roots {
+    // Real roots within [lo, hi] in increasing order, of the polynomial of the given degree, up
+    // to 4, with the coefficients the constant first and a non-zero leading one. Between the
+    // roots of the derivative the polynomial is monotonic, so each of those pieces holds at
+    // most one root, found by bisection where the sign changes. Touching roots without a sign
+    // change, from grazing rays, are missed.
+    let eval = |x: f64| {
+        coefficients[..=degree]
+            .iter()
+            .rev()
+            .fold(0.0, |acc, &c| acc * x + c)
+    };
+    let mut roots = Roots::default();
+    if degree == 1 {
+        let root = -coefficients[0] / coefficients[1];
+        if (lo..=hi).contains(&root) {
+            roots.push(root);
+        }
+        return roots;
+    }
+
+    let mut derivative = [0.0; 5];
+    for i in 1..=degree {
+        derivative[i - 1] = i as f64 * coefficients[i];
+    }
+    let turning = polynomial_roots(&derivative, degree - 1, lo, hi);
+    let mut bounds = [0.0; 5];
+    bounds[0] = lo;
+    bounds[1..=turning.len].copy_from_slice(turning.as_slice());
+    bounds[turning.len + 1] = hi;
+
+    for window in bounds[..turning.len + 2].windows(2) {
+        let (mut a, mut b) = (window[0], window[1]);
+        let (fa, fb) = (eval(a), eval(b));
+        if fa == 0.0 {
+            if roots.as_slice().last() != Some(&a) {
+                roots.push(a);
+            }
+            continue;
+        }
+        if fa.signum() == fb.signum() {
+            continue;
+        }
+        for _ in 0..60 {
+            let mid = 0.5 * (a + b);
+            if mid <= a || mid >= b {
+                break;
+            }
+            if eval(mid).signum() == fa.signum() {
+                a = mid;
+            } else {
+                b = mid;
+            }
+        }
+        roots.push(0.5 * (a + b));
+    }
+    roots
+}
+
+// The up to four roots of a quartic, kept on the stack since every hit test finds them.
+#[derive(Default)]
+struct Roots {
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn push(&mut self, root: f64) {
+        self.values[self.len] = root;
+        self.len += 1;
+    }
+
+    fn as_slice(&self) -> &[f64] {
+        &self.values[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grazing::{check_clear, check_tangent, check_through};
+
+    // Ring around the y axis with major radius 1 and minor radius 0.35, so its hole is 0.65
+    // across.
+    fn torus() -> Torus {
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        Torus::new(Vec3::default(), axis, 1.0, 0.35, Material::default())
+    }
+
+    fn distance(p: Vec3) -> f32 {
+        let radial = (p.x() * p.x() + p.z() * p.z()).sqrt();
+        ((radial - 1.0).powi(2) + p.y() * p.y()).sqrt() - 0.35
+    }
+
+    #[test]
+    fn through_hole_along_axis() {
+        // Rays along the axis through the hole miss, down to the one right on the axis, and
+        // those just past the inner wall hit it.
+        for direction in [1.0, -1.0] {
+            let along = |x: f32, far: f32| {
+                let origin = Vec3::new(x, -direction * far, 0.0);
+                Ray::new(origin, Vec3::new(0.0, direction, 0.0))
+            };
+            for x in [0.0, 0.3] {
+                check_clear(&torus(), &|far| along(x, far));
+            }
+            check_tangent(&torus(), &distance, &|offset, far| {
+                along(0.65 - offset, far)
+            });
+            check_through(&torus(), &distance, &|far| along(1.0, far));
+        }
+    }
+
+    #[test]
+    fn grazing_tube() {
+        // Tangent to the outside of the ring and to the top of the tube.
+        check_tangent(&torus(), &distance, &|offset, far| {
+            Ray::new(
+                Vec3::new(1.35 + offset, 0.0, -far),
+                Vec3::new(0.0, 0.0, 1.0),
+            )
+        });
+        check_tangent(&torus(), &distance, &|offset, far| {
+            Ray::new(
+                Vec3::new(-far, 0.35 + offset, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+            )
+        });
+    }
+}
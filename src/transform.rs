@@ -0,0 +1,376 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::mat4::Mat4;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+// An object placed in the world by an affine transform. The object is shared, so the same one,
+// say a large mesh, can be instanced many times without copying it.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    matrix: Mat4,  // Object to world space
+    inverse: Mat4, // World to object space
+    bbox: Aabb,
+}
+
+impl Transform {
+    pub fn new(object: impl Into<Arc<dyn Hittable>>, matrix: Mat4) -> Transform {
+        let object = object.into();
+        let inverse = matrix
+            .inverse()
+            .expect("transform matrix must be invertible");
+
+        let bbox = transformed_bbox(&object.bounding_box(), &matrix);
+
+        Transform {
+            object,
+            matrix,
+            inverse,
+            bbox,
+        }
+    }
+
+    pub fn translate(object: impl Into<Arc<dyn Hittable>>, offset: Vec3) -> Transform {
+        Transform::new(object, Mat4::translation(offset))
+    }
+
+    pub fn rotate_x(object: impl Into<Arc<dyn Hittable>>, degrees: f32) -> Transform {
+        Transform::new(object, Mat4::rotation_x(degrees))
+    }
+
+    pub fn rotate_y(object: impl Into<Arc<dyn Hittable>>, degrees: f32) -> Transform {
+        Transform::new(object, Mat4::rotation_y(degrees))
+    }
+
+    pub fn rotate_z(object: impl Into<Arc<dyn Hittable>>, degrees: f32) -> Transform {
+        Transform::new(object, Mat4::rotation_z(degrees))
+    }
+
+    pub fn scale(object: impl Into<Arc<dyn Hittable>>, factors: Vec3) -> Transform {
+        Transform::new(object, Mat4::scaling(factors))
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
+        hit_transformed(&*self.object, &self.matrix, &self.inverse, r, ray_t, depth)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        // The object's density is per object space solid angle. Mapping a unit direction w
+        // through the linear part L of the inverse scales solid angles by |det L| / |L w|^3.
+        let direction = Vec3::unit_vector(direction);
+        let object_direction = self.inverse.transform_vector(direction);
+        let pdf = self
+            .object
+            .pdf_value(self.inverse.transform_point(origin), object_direction);
+        pdf * self.inverse.determinant().abs() / object_direction.length().powi(3)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        let object_origin = self.inverse.transform_point(origin);
+        self.matrix
+            .transform_vector(self.object.random(object_origin))
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> f32 {
+        self.object
+            .transmittance(&object_ray(&self.inverse, r), ray_t, depth)
+    }
+}
+
+fn hit_transformed<'a>(
+    object: &'a dyn Hittable,
+    matrix: &Mat4,
+    inverse: &Mat4,
+    r: &Ray,
+    ray_t: Range<f32>,
+    depth: i32,
+) -> Option<HitRecord<'a>> {
+    let mut rec = object.hit(&object_ray(inverse, r), ray_t, depth)?;
+
+    rec.p = matrix.transform_point(rec.p);
+    rec.normal = Vec3::unit_vector(matrix.transform_normal(rec.normal));
+    Some(rec)
+}
+
+fn object_ray(inverse: &Mat4, r: &Ray) -> Ray {
+    // The object space direction isn't normalized, so distances along the ray, and with them
+    // t, stay the same in both spaces.
+    Ray::with_time(
+        inverse.transform_point(r.origin()),
+        inverse.transform_vector(r.direction()),
+        r.time(),
+    )
+}
+
+fn corners(bbox: &Aabb) -> Option<[Vec3; 8]> {
+    // None for the empty box.
+    if bbox.minimum.x() > bbox.maximum.x() {
+        return None;
+    }
+    Some(std::array::from_fn(|corner| {
+        let pick = |axis: usize| {
+            if corner & (1 << axis) == 0 {
+                bbox.minimum[axis]
+            } else {
+                bbox.maximum[axis]
+            }
+        };
+        Vec3::new(pick(0), pick(1), pick(2))
+    }))
+}
+
+fn transformed_bbox(bbox: &Aabb, matrix: &Mat4) -> Aabb {
+    // The world space box around the transformed corners of an object space box.
+    corners(bbox).map_or(Aabb::default(), |corners| {
+        corners.iter().fold(Aabb::default(), |acc, &corner| {
+            let p = matrix.transform_point(corner);
+            Aabb::surrounding(&acc, &Aabb::new(p, p))
+        })
+    })
+}
+
+// Number of steps the motion of an animated transform is sampled at to bound it.
+const MOTION_STEPS: usize = 64;
+
+// An object moving from one affine transform at time0 to another at time1. The translation,
+// rotation and scale of the two are interpolated separately, so a spinning object keeps its
+// shape instead of shrinking halfway like it would with the matrices blended directly.
+pub struct AnimatedTransform {
+    object: Arc<dyn Hittable>,
+    start: Decomposed,
+    end: Decomposed,
+    time0: f32,
+    time1: f32,
+    bbox: Aabb,
+}
+
+impl AnimatedTransform {
+    pub fn new(
+        object: impl Into<Arc<dyn Hittable>>,
+        start: Mat4,
+        end: Mat4,
+        time0: f32,
+        time1: f32,
+    ) -> AnimatedTransform {
+        let object = object.into();
+        let mut animated = AnimatedTransform {
+            object,
+            start: Decomposed::new(&start),
+            end: Decomposed::new(&end),
+            time0,
+            time1,
+            bbox: Aabb::default(),
+        };
+
+        // Bound the corners of the object's box along their paths by sampling them. Between
+        // samples a corner stays within half the distance it moved of the midpoint, as long as
+        // the steps are small enough to bend less than a half turn, so pad by that.
+        if let Some(corners) = corners(&animated.object.bounding_box()) {
+            let mut previous = corners.map(|c| start.transform_point(c));
+            let mut bbox = Aabb::default();
+            let mut pad: f32 = 0.0;
+            for step in 0..=MOTION_STEPS {
+                let matrix = animated
+                    .start
+                    .lerp(&animated.end, step as f32 / MOTION_STEPS as f32);
+                for (corner, previous) in corners.iter().zip(previous.iter_mut()) {
+                    let p = matrix.transform_point(*corner);
+                    pad = pad.max(0.5 * (p - *previous).length());
+                    bbox = Aabb::surrounding(&bbox, &Aabb::new(p, p));
+                    *previous = p;
+                }
+            }
+            let pad = Vec3::new(pad, pad, pad);
+            animated.bbox = Aabb::new(bbox.minimum - pad, bbox.maximum + pad);
+        }
+        animated
+    }
+
+    pub fn matrix(&self, time: f32) -> Mat4 {
+        // Rays from outside [time0, time1] see the object at the nearest end of its motion.
+        let s = if self.time1 > self.time0 {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.start.lerp(&self.end, s)
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> Option<HitRecord<'_>> {
+        let matrix = self.matrix(r.time());
+        let inverse = matrix.inverse()?;
+        hit_transformed(&*self.object, &matrix, &inverse, r, ray_t, depth)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, depth: i32) -> f32 {
+        match self.matrix(r.time()).inverse() {
+            Some(inverse) => self
+                .object
+                .transmittance(&object_ray(&inverse, r), ray_t, depth),
+            None => 1.0,
+        }
+    }
+
+    // Light sampling has no time to place the object at, so moving lights are only found by
+    // rays that hit them.
+}
+
+// An affine matrix split into translation, rotation and scale, M = T R S.
+#[derive(Debug, Clone, Copy)]
+struct Decomposed {
+    translation: Vec3,
+    rotation: [f32; 4], // Unit quaternion (x, y, z, w)
+    scale: Mat4,        // Symmetric, so it may shear too
+}
+
+impl Decomposed {
+    fn new(matrix: &Mat4) -> Decomposed {
+        let translation = matrix.transform_point(Vec3::default());
+        let mut linear = *matrix;
+        for row in &mut linear.e[..3] {
+            row[3] = 0.0;
+        }
+
+        // Polar decomposition: averaging a matrix with its inverse transpose converges to its
+        // rotation part. A mirroring matrix is negated first, leaving the mirror in the scale.
+        let sign = linear.determinant().signum();
+        let mut rotation = Mat4::scaling(Vec3::new(sign, sign, sign)) * linear;
+        for _ in 0..100 {
+            let Some(inverse) = rotation.inverse() else {
+                break;
+            };
+            let inverse_transpose = inverse.transpose();
+            let mut change: f32 = 0.0;
+            for i in 0..3 {
+                for j in 0..3 {
+                    let next = 0.5 * (rotation.e[i][j] + inverse_transpose.e[i][j]);
+                    change = change.max((next - rotation.e[i][j]).abs());
+                    rotation.e[i][j] = next;
+                }
+            }
+            if change < 1e-6 {
+                break;
+            }
+        }
+
+        Decomposed {
+            translation,
+            rotation: quaternion(&rotation),
+            scale: rotation.transpose() * linear,
+        }
+    }
+
+    fn lerp(&self, other: &Decomposed, s: f32) -> Mat4 {
+        let translation = self.translation + s * (other.translation - self.translation);
+        let mut scale = self.scale;
+        for (row, other_row) in scale.e.iter_mut().zip(&other.scale.e) {
+            for (value, other_value) in row.iter_mut().zip(other_row) {
+                *value += s * (other_value - *value);
+            }
+        }
+        let rotation = rotation_matrix(slerp(self.rotation, other.rotation, s));
+        Mat4::translation(translation) * rotation * scale
+    }
+}
+
+fn quaternion(m: &Mat4) -> [f32; 4] {
+    // Of a rotation matrix, from whichever of w, x, y or z is largest for precision.
+    let e = &m.e;
+    let trace = e[0][0] + e[1][1] + e[2][2];
+    if trace > 0.0 {
+        let s = 0.5 / (trace + 1.0).sqrt();
+        [
+            (e[2][1] - e[1][2]) * s,
+            (e[0][2] - e[2][0]) * s,
+            (e[1][0] - e[0][1]) * s,
+            0.25 / s,
+        ]
+    } else if e[0][0] > e[1][1] && e[0][0] > e[2][2] {
+        let s = 2.0 * (1.0 + e[0][0] - e[1][1] - e[2][2]).sqrt();
+        [
+            0.25 * s,
+            (e[0][1] + e[1][0]) / s,
+            (e[0][2] + e[2][0]) / s,
+            (e[2][1] - e[1][2]) / s,
+        ]
+    } else if e[1][1] > e[2][2] {
+        let s = 2.0 * (1.0 + e[1][1] - e[0][0] - e[2][2]).sqrt();
+        [
+            (e[0][1] + e[1][0]) / s,
+            0.25 * s,
+            (e[1][2] + e[2][1]) / s,
+            (e[0][2] - e[2][0]) / s,
+        ]
+    } else {
+        let s = 2.0 * (1.0 + e[2][2] - e[0][0] - e[1][1]).sqrt();
+        [
+            (e[0][2] + e[2][0]) / s,
+            (e[1][2] + e[2][1]) / s,
+            0.25 * s,
+            (e[1][0] - e[0][1]) / s,
+        ]
+    }
+}
+
+fn rotation_matrix(q: [f32; 4]) -> Mat4 {
+    let [x, y, z, w] = q;
+    Mat4::new([
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - z * w),
+            2.0 * (x * z + y * w),
+            0.0,
+        ],
+        [
+            2.0 * (x * y + z * w),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - x * w),
+            0.0,
+        ],
+        [
+            2.0 * (x * z - y * w),
+            2.0 * (y * z + x * w),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+        ],
+        [0.0, 0.0, 0.0, 1.0],
+    ])
+}
+
+fn slerp(a: [f32; 4], b: [f32; 4], s: f32) -> [f32; 4] {
+    // Along the shorter of the two arcs between the rotations.
+    let mut dot: f32 = (0..4).map(|i| a[i] * b[i]).sum();
+    let mut b = b;
+    if dot < 0.0 {
+        b = b.map(|c| -c);
+        dot = -dot;
+    }
+    let (wa, wb) = if dot > 0.9995 {
+        // Nearly the same rotation, where a straight line is as good and stays stable.
+        (1.0 - s, s)
+    } else {
+        let theta = dot.acos();
+        (
+            ((1.0 - s) * theta).sin() / theta.sin(),
+            (s * theta).sin() / theta.sin(),
+        )
+    };
+    let q: [f32; 4] = std::array::from_fn(|i| wa * a[i] + wb * b[i]);
+    let length = q.iter().map(|c| c * c).sum::<f32>().sqrt();
+    q.map(|c| c / length)
+}
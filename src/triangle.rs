@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::rng::random;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+    pub material: Material,
+    normal: Vec3,
+    area: f32,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Triangle {
+        // The outward normal follows the counter-clockwise winding of a, b, c.
+        let n = Vec3::cross(&(b - a), &(c - a));
+        Triangle {
+            a,
+            b,
+            c,
+            material,
+            normal: Vec3::unit_vector(n),
+            area: 0.5 * n.length(),
+        }
+    }
+}
+
+pub fn intersect_triangle(
+    r: &Ray,
+    ray_t: &Range<f32>,
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+) -> Option<(f32, f32, f32)> {
+    // Möller–Trumbore intersection. Returns the ray parameter t and the barycentric coordinates
+    // (u, v) of the hit point, weighting vertices b and c respectively.
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let pvec = Vec3::cross(&r.direction(), &edge2);
+    let det = Vec3::dot(&edge1, &pvec);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = r.origin() - a;
+    let u = Vec3::dot(&tvec, &pvec) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let qvec = Vec3::cross(&tvec, &edge1);
+    let v = Vec3::dot(&r.direction(), &qvec) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = Vec3::dot(&edge2, &qvec) * inv_det;
+    if (t <= ray_t.start) || (ray_t.end <= t) {
+        return None;
+    }
+
+    Some((t, u, v))
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        let (t, u, v) = intersect_triangle(r, &ray_t, self.a, self.b, self.c)?;
+        let (front_face, normal) = face_normal(r, self.normal);
+
+        Some(HitRecord {
+            p: r.at(t),
+            normal,
+            material: &self.material,
+            t,
+            u,
+            v,
+            front_face,
+            color: None,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::surrounding(&Aabb::new(self.a, self.b), &Aabb::new(self.c, self.c)).pad()
+    }
+
+    fn pdf_value(&self, origin: Vec3, direction: Vec3) -> f32 {
+        // Convert the uniform density over the area to a density over solid angle.
+        let ray_t = Range {
+            start: 0.001,
+            end: f32::INFINITY,
+        };
+        let Some(rec) = self.hit(&Ray::new(origin, direction), ray_t, 0) else {
+            return 0.0;
+        };
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (Vec3::dot(&direction, &self.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Vec3) -> Vec3 {
+        // Uniform point on the triangle, folding the unit square onto it.
+        let mut u = random::<f32>();
+        let mut v = random::<f32>();
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        let p = self.a + u * (self.b - self.a) + v * (self.c - self.a);
+        p - origin
+    }
+}
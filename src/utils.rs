@@ -1,37 +1,371 @@
+use crate::background::Background;
 use crate::hittable::*;
 use crate::hittable_list::HittableList;
-use crate::material::scatter;
+use crate::light::Light;
+use crate::material::{emitted, is_phase_function, scatter, scattering};
+use crate::medium::Fog;
 use crate::ray::Ray;
+use crate::rng::random;
 use crate::vec3::Vec3;
 
+use std::f32::consts::PI;
 use std::ops::Range;
 
-pub fn color(r: &Ray, depth: i32, world: &HittableList) -> Vec3 {
+pub fn color(
+    r: &Ray,
+    depth: i32,
+    world: &dyn Hittable,
+    lights: &HittableList,
+    delta_lights: &[Box<dyn Light>],
+    background: &Background,
+    fog: Option<&Fog>,
+) -> Vec3 {
+    trace(r, depth, world, lights, delta_lights, background, fog, None)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn trace(
+    r: &Ray,
+    depth: i32,
+    world: &dyn Hittable,
+    lights: &HittableList,
+    delta_lights: &[Box<dyn Light>],
+    background: &Background,
+    fog: Option<&Fog>,
+    scattered_pdf: Option<f32>,
+) -> Vec3 {
+    // `scattered_pdf` is set when `r` was scattered off a surface or medium that also sampled the
+    // lights directly, so emission found along it gets its multiple importance sampling weight.
     if depth <= 0 {
         return Vec3::new(0.0, 0.0, 0.0);
     }
 
-    if let Some(rec) = world.hit(
-        r,
+    let Some(rec) = world.hit(r, hit_range(), depth) else {
+        let mut color_from_background = background.value(r);
+        if let (Some(pdf), true) = (scattered_pdf, background.is_sampled()) {
+            let light_pdf = light_pdf(r.origin(), r.direction(), lights, background);
+            color_from_background = power_heuristic(pdf, light_pdf) * color_from_background;
+        }
+        return color_from_background;
+    };
+
+    // Fog scatters the ray at an exponentially distributed distance on its way to the hit.
+    // Rays that miss everything have left the fog, and see the background unchanged.
+    if let Some(fog) = fog {
+        let length = r.direction().length();
+        let distance = fog.sample_distance();
+        if distance < rec.t * length {
+            let p = r.at(distance / length);
+            let pdf = 1.0 / (4.0 * PI);
+            let phase_function = |_| (pdf * fog.albedo, pdf);
+            let scattered = Ray::with_time(p, Vec3::random_unit_vector(), r.time());
+            let color_from_lights = if has_lights(lights, background) {
+                let fog = Some(fog);
+                sample_lights(
+                    p,
+                    r.time(),
+                    depth,
+                    world,
+                    lights,
+                    background,
+                    fog,
+                    phase_function,
+                )
+            } else {
+                Vec3::default()
+            };
+            let color_from_delta_lights = sample_delta_lights(
+                p,
+                r.time(),
+                depth,
+                world,
+                delta_lights,
+                Some(fog),
+                phase_function,
+            );
+            let color_from_scatter = trace(
+                &scattered,
+                depth - 1,
+                world,
+                lights,
+                delta_lights,
+                background,
+                Some(fog),
+                Some(pdf),
+            );
+            return color_from_lights + color_from_delta_lights + fog.albedo * color_from_scatter;
+        }
+    }
+
+    let mut scattered = Ray::new(Vec3::default(), Vec3::default());
+    let mut attenuation = Vec3::default();
+
+    let mut color_from_emission = emitted(rec.material, &rec);
+    if let Some(pdf) = scattered_pdf {
+        let light_pdf = light_pdf(r.origin(), r.direction(), lights, background);
+        color_from_emission = power_heuristic(pdf, light_pdf) * color_from_emission;
+    }
+
+    if !scatter(rec.material, r, &rec, &mut attenuation, &mut scattered) {
+        return color_from_emission;
+    }
+
+    // Materials without a scattering density, like mirrors and glass, can't be lit by delta
+    // lights: no direction they scatter in ends exactly at one.
+    let pdf = scattering(rec.material, r, &rec, scattered.direction()).map(|(_, pdf)| pdf);
+    let material_scattering =
+        |d| scattering(rec.material, r, &rec, d).unwrap_or((Vec3::default(), 0.0));
+    let color_from_delta_lights = if pdf.is_some() {
+        sample_delta_lights(
+            rec.p,
+            r.time(),
+            depth,
+            world,
+            delta_lights,
+            fog,
+            material_scattering,
+        )
+    } else {
+        Vec3::default()
+    };
+
+    if let (Some(pdf), true) = (pdf, has_lights(lights, background)) {
+        let color_from_lights = sample_lights(
+            rec.p,
+            r.time(),
+            depth,
+            world,
+            lights,
+            background,
+            fog,
+            material_scattering,
+        );
+        let color_from_scatter = trace(
+            &scattered,
+            depth - 1,
+            world,
+            lights,
+            delta_lights,
+            background,
+            fog,
+            Some(pdf),
+        );
+
+        color_from_emission
+            + color_from_lights
+            + color_from_delta_lights
+            + attenuation * color_from_scatter
+    } else {
+        let color_from_scatter = trace(
+            &scattered,
+            depth - 1,
+            world,
+            lights,
+            delta_lights,
+            background,
+            fog,
+            None,
+        );
+
+        color_from_emission + color_from_delta_lights + attenuation * color_from_scatter
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sample_lights(
+    origin: Vec3,
+    time: f32,
+    depth: i32,
+    world: &dyn Hittable,
+    lights: &HittableList,
+    background: &Background,
+    fog: Option<&Fog>,
+    scattering: impl Fn(Vec3) -> (Vec3, f32),
+) -> Vec3 {
+    // Direct lighting at a scattering point from a shadow ray towards a random point on a light,
+    // or a bright part of the background. `scattering` gives the fraction of the light from a
+    // direction scattered along the path, and the density of scattering in that direction.
+    let sample_background = background.is_sampled() && (lights.is_empty() || random::<f32>() < 0.5);
+    let direction = if sample_background {
+        background.random()
+    } else {
+        lights.random(origin)
+    };
+    let light_pdf = light_pdf(origin, direction, lights, background);
+    let (scattered, scattering_pdf) = scattering(direction);
+    if light_pdf <= 0.0 || scattering_pdf <= 0.0 {
+        return Vec3::default();
+    }
+
+    // The shadow ray passes through media, which scatter some of the light away on the way, and
+    // stops at the first surface. Rays escaping the scene see the background, if it is sampled,
+    // and have left the fog.
+    let shadow_ray = Ray::with_time(origin, direction, time);
+    let mut range = hit_range();
+    let light_rec = loop {
+        match world.hit(&shadow_ray, range.clone(), depth) {
+            Some(rec) if is_phase_function(rec.material) => range.start = rec.t,
+            Some(rec) => break Some(rec),
+            None if background.is_sampled() => break None,
+            None => return Vec3::default(),
+        }
+    };
+    let (end, radiance) = match &light_rec {
+        Some(rec) => (rec.t, emitted(rec.material, rec)),
+        None => (f32::INFINITY, background.value(&shadow_ray)),
+    };
+
+    let weight = power_heuristic(light_pdf, scattering_pdf);
+    let mut transmittance = world.transmittance(
+        &shadow_ray,
         Range {
-            start: 0.001,
-            end: std::f32::INFINITY,
+            start: hit_range().start,
+            end,
         },
         depth,
-    ) {
-        let mut scattered = Ray::ray(Vec3::default(), Vec3::default());
-        let mut attenuation = Vec3::default();
+    );
+    if let (Some(fog), true) = (fog, light_rec.is_some()) {
+        transmittance *= fog.transmittance(end * direction.length());
+    }
+    (weight * transmittance / light_pdf) * scattered * radiance
+}
+
+fn sample_delta_lights(
+    origin: Vec3,
+    time: f32,
+    depth: i32,
+    world: &dyn Hittable,
+    delta_lights: &[Box<dyn Light>],
+    fog: Option<&Fog>,
+    scattering: impl Fn(Vec3) -> (Vec3, f32),
+) -> Vec3 {
+    // Direct lighting at a scattering point from a shadow ray towards each delta light. The
+    // light comes from a single direction, so there is no density to divide by, and no other
+    // way to find it to weigh against.
+    let mut color = Vec3::default();
+    for light in delta_lights {
+        let Some(illumination) = light.illuminate(origin) else {
+            continue;
+        };
+        let (scattered, _) = scattering(illumination.direction);
+        if scattered.near_zero() {
+            continue;
+        }
+
+        // Any surface before the light blocks it, and media on the way scatter some of it away.
+        let shadow_ray = Ray::with_time(origin, illumination.direction, time);
+        let range = Range {
+            start: hit_range().start,
+            end: illumination.distance,
+        };
+        let mut start = range.start;
+        let blocked = loop {
+            match world.hit(&shadow_ray, start..range.end, depth) {
+                Some(rec) if is_phase_function(rec.material) => start = rec.t,
+                Some(_) => break true,
+                None => break false,
+            }
+        };
+        if blocked {
+            continue;
+        }
+        // Like the background, directional lights are outside the fog.
+        let mut transmittance = world.transmittance(&shadow_ray, range, depth);
+        if let (Some(fog), true) = (fog, illumination.distance.is_finite()) {
+            transmittance *= fog.transmittance(illumination.distance);
+        }
+        color = color + transmittance * scattered * illumination.irradiance;
+    }
+    color
+}
+
+fn has_lights(lights: &HittableList, background: &Background) -> bool {
+    !lights.is_empty() || background.is_sampled()
+}
 
-        if scatter(&rec.material, &r, &rec, &mut attenuation, &mut scattered) {
-            return attenuation * color(&scattered, depth - 1, &world);
-        } else {
-            return Vec3::default();
+fn light_pdf(origin: Vec3, direction: Vec3, lights: &HittableList, background: &Background) -> f32 {
+    // Density of the directions sample_lights draws, from the lights and the background each
+    // half the time when there are both.
+    match (lights.is_empty(), background.is_sampled()) {
+        (false, true) => {
+            0.5 * (lights.pdf_value(origin, direction) + background.pdf_value(direction))
         }
+        (false, false) => lights.pdf_value(origin, direction),
+        (true, true) => background.pdf_value(direction),
+        (true, false) => 0.0,
+    }
+}
+
+fn hit_range() -> Range<f32> {
+    Range {
+        start: 0.001,
+        end: f32::INFINITY,
+    }
+}
+
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    // Weight of a sample drawn from `pdf` when `other_pdf` could have produced it too.
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    if a + b > 0.0 {
+        a / (a + b)
     } else {
-        let unit_direction: Vec3 = Vec3::unit_vector(r.direction());
-        let a: f32 = 0.5 * (unit_direction.y() + 1.0);
+        0.0
+    }
+}
+
+pub fn solve_quadratic(a: f32, half_b: f32, c: f32) -> Option<(f32, f32)> {
+    // Real roots of a t^2 + 2 half_b t + c, smallest first. The root away from zero comes from
+    // adding numbers of the same sign and the other from the product of the roots, which avoids
+    // the cancellation of the textbook formula for grazing rays. With a = 0 the one root is
+    // finite and the other infinite.
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let q = -(half_b + half_b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return (a != 0.0).then_some((0.0, 0.0));
+    }
+    let (t0, t1) = (q / a, c / q);
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+// Keeps the nearest of the roots a shape's hit test finds along a ray, with what the shape
+// needs about each. Far origins lose the precision of the quadratics' constant terms, so the
+// origin is first moved along the ray next to the shape's middle, the roots counted from there
+// and shifted back at the end.
+pub struct NearestRoot<T> {
+    pub origin: Vec3, // The moved origin
+    shift: f32,
+    ray_t: Range<f32>, // Counted from the moved origin
+    nearest: Option<(f32, T)>,
+}
+
+impl<T> NearestRoot<T> {
+    pub fn new(origin: Vec3, direction: Vec3, middle: Vec3, ray_t: Range<f32>) -> NearestRoot<T> {
+        let shift = Vec3::dot(&(middle - origin), &direction) / direction.length_squared();
+        NearestRoot {
+            origin: origin + shift * direction,
+            shift,
+            ray_t: (ray_t.start - shift)..(ray_t.end - shift),
+            nearest: None,
+        }
+    }
+
+    pub fn consider(&mut self, t: f32, hit: T) {
+        // t counted from the moved origin.
+        if self.ray_t.start < t
+            && t < self.ray_t.end
+            && self.nearest.as_ref().is_none_or(|(best, _)| t < *best)
+        {
+            self.nearest = Some((t, hit));
+        }
+    }
 
-        (1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0)
+    pub fn nearest(self) -> Option<(f32, T)> {
+        // The nearest root with t along the original ray.
+        self.nearest.map(|(t, hit)| (t + self.shift, hit))
     }
 }
 
@@ -39,5 +373,5 @@ pub fn reflectance(cosine: f32, ir: f32) -> f32 {
     // Use Schlick's approximation for reflectance.
     let r0 = ((1.0 - ir) / (1.0 + ir)).powi(2);
 
-    return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
@@ -1,4 +1,4 @@
-use rand::Rng;
+use crate::rng::random_range;
 use std::ops;
 use std::ops::Neg;
 
@@ -47,7 +47,7 @@ impl Vec3 {
     pub fn near_zero(self) -> bool {
         // Return true if the vector is close to zero in all dimensions.
         let s: f32 = 1e-8;
-        return (self.e[0].abs() < s) && (self.e[1].abs() < s) && (self.e[2].abs() < s);
+        (self.e[0].abs() < s) && (self.e[1].abs() < s) && (self.e[2].abs() < s)
     }
 
     pub fn unit_vector(v: Vec3) -> Vec3 {
@@ -65,11 +65,7 @@ impl Vec3 {
 
     pub fn random_in_unit_disk() -> Vec3 {
         loop {
-            let p = Vec3::new(
-                rand::thread_rng().gen_range(-1.0..1.0),
-                rand::thread_rng().gen_range(-1.0..1.0),
-                0.0,
-            );
+            let p = Vec3::new(random_range(-1.0..1.0), random_range(-1.0..1.0), 0.0);
             if p.length_squared() < 1.0 {
                 return p;
             }
@@ -77,7 +73,7 @@ impl Vec3 {
     }
 
     pub fn random_unit_vector() -> Vec3 {
-        return Vec3::unit_vector(Vec3::random_in_unit_sphere());
+        Vec3::unit_vector(Vec3::random_in_unit_sphere())
     }
 
     pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
@@ -91,7 +87,7 @@ impl Vec3 {
     }
 
     pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
-        return v - 2.0 * Vec3::dot(&v, &n) * n;
+        v - 2.0 * Vec3::dot(&v, &n) * n
     }
 
     pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f32) -> Vec3 {
@@ -99,7 +95,7 @@ impl Vec3 {
         let r_out_perp = etai_over_etat * (uv + cos_theta * n);
         let r_out_parallel = (1.0 - r_out_perp.length_squared()).max(1.0).sqrt().neg() * n;
 
-        return r_out_parallel + r_out_perp;
+        r_out_parallel + r_out_perp
     }
 
     pub fn dot(v1: &Vec3, v2: &Vec3) -> f32 {
@@ -107,19 +103,25 @@ impl Vec3 {
     }
 
     pub fn cross(u: &Vec3, v: &Vec3) -> Vec3 {
-        return Vec3::new(
+        Vec3::new(
             u.e[1] * v.e[2] - u.e[2] * v.e[1],
             u.e[2] * v.e[0] - u.e[0] * v.e[2],
             u.e[0] * v.e[1] - u.e[1] * v.e[0],
-        );
+        )
     }
 
     pub fn random(min: f32, max: f32) -> Vec3 {
-        return Vec3::new(
-            rand::thread_rng().gen_range(min..=max),
-            rand::thread_rng().gen_range(min..=max),
-            rand::thread_rng().gen_range(min..=max),
-        );
+        Vec3::new(
+            random_range(min..=max),
+            random_range(min..=max),
+            random_range(min..=max),
+        )
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from(e: [f32; 3]) -> Vec3 {
+        Vec3 { e }
     }
 }
 
@@ -207,6 +209,14 @@ impl ops::Mul<Vec3> for f32 {
     }
 }
 
+impl ops::Index<usize> for Vec3 {
+    type Output = f32;
+
+    fn index(&self, i: usize) -> &f32 {
+        &self.e[i]
+    }
+}
+
 impl ops::Neg for Vec3 {
     type Output = Vec3;
 
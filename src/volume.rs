@@ -0,0 +1,272 @@
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::*;
+use crate::material::Material;
+use crate::perlin::Perlin;
+use crate::rng::random;
+use crate::texture::SolidColor;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+// Density of a heterogeneous medium over the unit cube, scaled by the medium's coefficients.
+pub trait DensityField: Send + Sync {
+    fn density(&self, p: Vec3) -> f32;
+
+    // Upper bound of the density, the majorant tracking samples against.
+    fn max_density(&self) -> f32;
+}
+
+// Densities on a regular grid of voxels, interpolated trilinearly between the voxel centers.
+pub struct VoxelGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>, // x varies fastest, then y, then z
+    max: f32,
+}
+
+impl VoxelGrid {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> VoxelGrid {
+        assert_eq!(
+            values.len(),
+            resolution.iter().product::<usize>(),
+            "voxel count must match the grid resolution"
+        );
+        let max = values.iter().fold(0.0, |max: f32, &v| max.max(v));
+        VoxelGrid {
+            resolution,
+            values,
+            max,
+        }
+    }
+
+    // Raw voxel files hold nothing but the densities as little endian 32 bit floats, in the
+    // grid's order. Negative densities are read as empty space.
+    pub fn load(path: &Path, resolution: [usize; 3]) -> io::Result<VoxelGrid> {
+        let bytes = fs::read(path)?;
+        let [nx, ny, nz] = resolution;
+        let Some(size) = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .and_then(|n| n.checked_mul(4))
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{nx} x {ny} x {nz} voxels are too many to load"),
+            ));
+        };
+        if size == 0 || bytes.len() != size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected {size} bytes for {nx} x {ny} x {nz} voxels, found {}",
+                    bytes.len()
+                ),
+            ));
+        }
+
+        let values: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        if values.iter().any(|v| !v.is_finite()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "voxel densities must be finite",
+            ));
+        }
+        Ok(VoxelGrid::new(
+            resolution,
+            values.into_iter().map(|v| v.max(0.0)).collect(),
+        ))
+    }
+
+    fn voxel(&self, i: usize, j: usize, k: usize) -> f32 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(k * ny + j) * nx + i]
+    }
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: Vec3) -> f32 {
+        // The neighbouring voxels and the weights of the upper ones, clamped at the faces.
+        let split = |axis: usize| {
+            let n = self.resolution[axis];
+            let x = (p[axis] * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            let i = (x as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), x - i as f32)
+        };
+        let (i0, i1, fx) = split(0);
+        let (j0, j1, fy) = split(1);
+        let (k0, k1, fz) = split(2);
+
+        let lerp = |a: f32, b: f32, t: f32| a + t * (b - a);
+        let plane = |k| {
+            lerp(
+                lerp(self.voxel(i0, j0, k), self.voxel(i1, j0, k), fx),
+                lerp(self.voxel(i0, j1, k), self.voxel(i1, j1, k), fx),
+                fy,
+            )
+        };
+        lerp(plane(k0), plane(k1), fz)
+    }
+
+    fn max_density(&self) -> f32 {
+        self.max
+    }
+}
+
+// Procedural density from fractal noise, between 0 and 1. Only the positive half of the noise
+// is kept, and steepened, leaving puffs of medium with empty space between them.
+pub struct NoiseDensity {
+    noise: Perlin,
+    scale: f32,
+    octaves: i32,
+}
+
+impl NoiseDensity {
+    pub fn new(scale: f32, octaves: i32) -> NoiseDensity {
+        NoiseDensity {
+            noise: Perlin::new(),
+            scale,
+            octaves,
+        }
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, p: Vec3) -> f32 {
+        let n = self.noise.fbm(self.scale * p, self.octaves);
+        (2.0 * n).clamp(0.0, 1.0)
+    }
+
+    fn max_density(&self) -> f32 {
+        1.0
+    }
+}
+
+// A medium of varying density filling an axis-aligned box, like a cloud. The density field is
+// stretched over the box, and scales the absorption and scattering coefficients, the chances of
+// absorbing and scattering light per unit of distance. Collisions are found by delta tracking
+// and the light passing through by ratio tracking, both against the largest density.
+pub struct HeterogeneousMedium {
+    bounds: Aabb,
+    field: Box<dyn DensityField>,
+    extinction: f32, // Absorption plus scattering
+    majorant: f32,
+    phase_function: Material,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        bounds: Aabb,
+        field: Box<dyn DensityField>,
+        absorption: f32,
+        scattering: f32,
+        g: f32,
+    ) -> HeterogeneousMedium {
+        let extinction = absorption + scattering;
+        let albedo = if extinction > 0.0 {
+            scattering / extinction
+        } else {
+            0.0
+        };
+        let majorant = extinction * field.max_density();
+        HeterogeneousMedium {
+            bounds,
+            field,
+            extinction,
+            majorant,
+            phase_function: Material::HenyeyGreenstein {
+                albedo: Arc::new(SolidColor::new(Vec3::new(albedo, albedo, albedo))),
+                g: g.clamp(-0.99, 0.99),
+            },
+        }
+    }
+
+    fn inside(&self, r: &Ray, ray_t: Range<f32>) -> Option<Range<f32>> {
+        // Slab test keeping the interval of the ray inside the box.
+        let mut t_min = ray_t.start.max(0.0);
+        let mut t_max = ray_t.end;
+        for axis in 0..3 {
+            let inv_d = r.direction()[axis].recip();
+            let mut t0 = (self.bounds.minimum[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.bounds.maximum[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN bounds, from rays lying in a face, leave the interval as it is.
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+        }
+        (t_min < t_max && self.majorant > 0.0).then_some(t_min..t_max)
+    }
+
+    fn extinction_at(&self, p: Vec3) -> f32 {
+        let size = self.bounds.maximum - self.bounds.minimum;
+        let local = p - self.bounds.minimum;
+        let local = Vec3::new(
+            local.x() / size.x(),
+            local.y() / size.y(),
+            local.z() / size.z(),
+        );
+        self.extinction * self.field.density(local)
+    }
+
+    fn step(&self, r: &Ray) -> f32 {
+        // Distance in t to the next tentative collision in a medium as dense as the majorant.
+        -(1.0 - random::<f32>()).ln() / (self.majorant * r.direction().length())
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> Option<HitRecord<'_>> {
+        // Delta tracking: tentative collisions are real with the ratio of the density there to
+        // the majorant, and skipped otherwise.
+        let inside = self.inside(r, ray_t)?;
+        let mut t = inside.start;
+        loop {
+            t += self.step(r);
+            if t >= inside.end {
+                return None;
+            }
+            let p = r.at(t);
+            if random::<f32>() * self.majorant < self.extinction_at(p) {
+                return Some(HitRecord {
+                    p,
+                    normal: Vec3::new(1.0, 0.0, 0.0), // Arbitrary, media have no surface
+                    material: &self.phase_function,
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    front_face: true,
+                    color: None,
+                });
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Range<f32>, _depth: i32) -> f32 {
+        // Ratio tracking: every tentative collision keeps the fraction of light that a real one
+        // would have stopped.
+        let Some(inside) = self.inside(r, ray_t) else {
+            return 1.0;
+        };
+        let mut transmittance = 1.0;
+        let mut t = inside.start;
+        loop {
+            t += self.step(r);
+            if t >= inside.end {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.extinction_at(r.at(t)) / self.majorant;
+        }
+    }
+}